@@ -3,10 +3,48 @@
 extern crate coco;
 extern crate test;
 
-use coco::epoch;
+use std::thread;
+
+use coco::{epoch, spsc};
 use test::Bencher;
 
 #[bench]
 fn pin_empty(b: &mut Bencher) {
     b.iter(|| epoch::pin(|_| ()))
 }
+
+#[bench]
+fn spsc_push_pop(b: &mut Bencher) {
+    let (p, c) = spsc::channel(1);
+    b.iter(|| {
+        p.push(1).unwrap();
+        c.pop().unwrap()
+    })
+}
+
+#[bench]
+fn spsc_throughput(b: &mut Bencher) {
+    const COUNT: usize = 100_000;
+
+    b.iter(|| {
+        let (p, c) = spsc::channel(1024);
+
+        let t = thread::spawn(move || {
+            for i in 0..COUNT {
+                let mut value = i;
+                while let Err(v) = p.push(value) {
+                    value = v;
+                    thread::yield_now();
+                }
+            }
+        });
+
+        for _ in 0..COUNT {
+            while c.pop().is_none() {
+                thread::yield_now();
+            }
+        }
+
+        t.join().unwrap();
+    })
+}
@@ -0,0 +1,53 @@
+extern crate coco;
+extern crate rand;
+
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::SeqCst;
+use std::thread;
+
+use coco::queue::PriorityQueue;
+use rand::Rng;
+
+#[test]
+fn sanitize_priority_queue() {
+    const THREADS: usize = 8;
+
+    let q = Arc::new(PriorityQueue::new());
+    let len = Arc::new(AtomicUsize::new(0));
+
+    let threads = (0..THREADS).map(|t| {
+        let q = q.clone();
+        let len = len.clone();
+
+        thread::spawn(move || {
+            let mut rng = rand::thread_rng();
+            for i in 0..100_000 {
+                if rng.gen_range(0, t + 1) == 0 {
+                    if q.pop_min().is_some() {
+                        len.fetch_sub(1, SeqCst);
+                    }
+                } else {
+                    q.push(t + THREADS * i);
+                    len.fetch_add(1, SeqCst);
+                }
+            }
+        })
+    }).collect::<Vec<_>>();
+
+    for t in threads {
+        t.join().unwrap();
+    }
+
+    let mut last = 0;
+
+    while !q.is_empty() {
+        let x = q.pop_min().unwrap();
+
+        assert!(last <= x);
+        last = x;
+
+        len.fetch_sub(1, SeqCst);
+    }
+    assert_eq!(len.load(SeqCst), 0);
+}
@@ -53,23 +53,22 @@
 //! }
 //! ```
 
+use std::cell::UnsafeCell;
 use std::cmp;
 use std::fmt;
 use std::marker::PhantomData;
-use std::mem;
+use std::mem::{self, MaybeUninit};
 use std::ptr;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicIsize, fence};
-use std::sync::atomic::Ordering::{Acquire, Release, Relaxed, SeqCst};
+use std::sync::atomic::{AtomicIsize, AtomicUsize, fence};
+use std::sync::atomic::Ordering::{AcqRel, Acquire, Release, Relaxed, SeqCst};
 
+use CACHE_LINE_BYTES;
 use epoch::{self, Atomic, Owned};
 
 /// Minimum buffer capacity for a deque.
 const DEFAULT_MIN_CAP: usize = 16;
 
-/// Typical cache line size in bytes on modern machines.
-const CACHE_LINE_BYTES: usize = 64;
-
 /// When weakly stealing some data, this is an enumeration of the possible outcomes.
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
 pub enum Steal<T> {
@@ -84,6 +83,61 @@ pub enum Steal<T> {
     Inconsistent,
 }
 
+/// Statistics on the outcomes of steal operations performed by a stealer.
+///
+/// This is only available with the `stats` feature.
+#[cfg(feature = "stats")]
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct StealStats {
+    /// Number of successfully stolen elements.
+    pub stolen: usize,
+
+    /// Number of steal attempts that failed because another concurrent operation got in the way.
+    pub inconsistent: usize,
+
+    /// Number of times the deque was observed to be empty.
+    pub empty: usize,
+}
+
+/// Counters of steal outcomes, kept by each stealer.
+///
+/// Without the `stats` feature this is a zero-sized type and recording does nothing.
+#[derive(Default)]
+struct Counters {
+    #[cfg(feature = "stats")]
+    stolen: AtomicUsize,
+    #[cfg(feature = "stats")]
+    inconsistent: AtomicUsize,
+    #[cfg(feature = "stats")]
+    empty: AtomicUsize,
+}
+
+impl Counters {
+    /// Records the outcome of a single steal attempt.
+    #[inline]
+    fn record<T>(&self, _steal: &Steal<T>) {
+        #[cfg(feature = "stats")]
+        {
+            let counter = match *_steal {
+                Steal::Empty => &self.empty,
+                Steal::Data(_) => &self.stolen,
+                Steal::Inconsistent => &self.inconsistent,
+            };
+            counter.fetch_add(1, Relaxed);
+        }
+    }
+
+    /// Returns a snapshot of the counters.
+    #[cfg(feature = "stats")]
+    fn stats(&self) -> StealStats {
+        StealStats {
+            stolen: self.stolen.load(Relaxed),
+            inconsistent: self.inconsistent.load(Relaxed),
+            empty: self.empty.load(Relaxed),
+        }
+    }
+}
+
 /// A buffer where deque elements are stored.
 struct Buffer<T> {
     /// Pointer to the allocated memory.
@@ -129,6 +183,14 @@ impl<T> Drop for Buffer<T> {
     }
 }
 
+/// A callback invoked with the old and the new capacity whenever the buffer grows.
+type GrowCallback = Box<Fn(usize, usize) + Send>;
+
+/// The internal representation of a work-stealing deque.
+///
+/// The bottom and top indices only ever grow and are allowed to overflow. All arithmetic on them
+/// wraps around, and buffer slots are found by masking the index with the capacity, so a deque
+/// keeps working correctly no matter how many elements go through it.
 #[repr(C)]
 struct Deque<T> {
     /// The bottom index.
@@ -143,6 +205,12 @@ struct Deque<T> {
     /// Minimal capacity of the buffer. Always a power of two.
     min_cap: usize,
 
+    /// Whether the worker pops from the top rather than the bottom of the deque.
+    fifo: bool,
+
+    /// The callback invoked when the buffer grows. Only the worker may access it.
+    on_grow: UnsafeCell<Option<GrowCallback>>,
+
     /// Some padding to avoid false sharing.
     _pad0: [u8; CACHE_LINE_BYTES],
 }
@@ -164,10 +232,21 @@ impl<T> Deque<T> {
             top: AtomicIsize::new(0),
             buffer: Atomic::new(Buffer::new(cap)),
             min_cap: cap,
+            fifo: false,
+            on_grow: UnsafeCell::new(None),
             _pad0: [0; CACHE_LINE_BYTES],
         }
     }
 
+    /// Returns a new, empty deque whose bottom and top indices both start at `index`.
+    #[cfg(test)]
+    fn with_start_index(index: isize) -> Self {
+        let d = Self::new();
+        d.bottom.store(index, Relaxed);
+        d.top.store(index, Relaxed);
+        d
+    }
+
     /// Returns the number of elements in the deque.
     ///
     /// If used concurrently with other operations, the returned number is just an estimate.
@@ -186,6 +265,7 @@ impl<T> Deque<T> {
         let t = self.top.load(Relaxed);
 
         let buffer = epoch::unprotected(|scope| self.buffer.load(Relaxed, scope).as_raw());
+        let old_cap = (*buffer).cap;
 
         // Allocate a new buffer.
         let new = Buffer::new(new_cap);
@@ -209,7 +289,43 @@ impl<T> Deque<T> {
             if mem::size_of::<T>() * new_cap >= 1 << 10 {
                 scope.flush();
             }
-        })
+        });
+
+        if new_cap > old_cap {
+            if let Some(ref f) = *self.on_grow.get() {
+                f(old_cap, new_cap);
+            }
+        }
+    }
+
+    /// Reserves capacity for at least `additional` more elements, growing the buffer at most once.
+    ///
+    /// Only the worker may call this method.
+    fn reserve(&self, additional: usize) {
+        // Load the bottom, top, and buffer. The buffer doesn't have to be epoch-protected because
+        // the current thread (the worker) is the only one that grows and shrinks it.
+        let b = self.bottom.load(Relaxed);
+        let t = self.top.load(Acquire);
+
+        let buffer = unsafe {
+            epoch::unprotected(|scope| self.buffer.load(Relaxed, scope).as_raw())
+        };
+
+        // Calculate the length of the deque and the capacity needed to hold the new elements.
+        let len = cmp::max(b.wrapping_sub(t), 0) as usize;
+        let required = len.saturating_add(additional);
+
+        // Grow the buffer only if it can't already hold all the elements.
+        let cap = unsafe { (*buffer).cap };
+        if required > cap {
+            let new_cap = match required.checked_next_power_of_two() {
+                Some(new_cap) => new_cap,
+                None => panic!("capacity overflow"),
+            };
+            unsafe {
+                self.resize(new_cap);
+            }
+        }
     }
 
     /// Pushes an element onto the bottom of the deque.
@@ -276,6 +392,11 @@ impl<T> Deque<T> {
             let mut value = unsafe { Some((*a).read(b)) };
 
             // Are we popping the last element from the deque?
+            //
+            // If so, a stealer might be trying to take the same element right now. The stealer
+            // reads the value and then increments the top with a CAS, so the worker must do the
+            // same instead of just keeping the decremented bottom. Exactly one of the CASes
+            // succeeds, and the loser forgets its copy of the value.
             if len == 0 {
                 // Try incrementing the top.
                 if self.top.compare_exchange(t, t.wrapping_add(1), SeqCst, Relaxed).is_err() {
@@ -299,8 +420,8 @@ impl<T> Deque<T> {
         }
     }
 
-    /// Steals an element from the top of the deque.
-    fn steal(&self, weak: bool) -> Steal<T> {
+    /// Steals an element from the top of the deque and records outcomes into `counters`.
+    fn steal(&self, weak: bool, counters: &Counters) -> Steal<T> {
         // Load the top.
         let mut t = self.top.load(Acquire);
 
@@ -319,6 +440,7 @@ impl<T> Deque<T> {
 
                 // Is the deque empty?
                 if b.wrapping_sub(t) <= 0 {
+                    counters.record(&Steal::Empty::<T>);
                     return Steal::Empty;
                 }
 
@@ -328,11 +450,14 @@ impl<T> Deque<T> {
 
                 // Try incrementing the top to steal the value.
                 if self.top.compare_exchange(t, t.wrapping_add(1), SeqCst, Relaxed).is_ok() {
-                    return Steal::Data(value);
+                    let steal = Steal::Data(value);
+                    counters.record(&steal);
+                    return steal;
                 }
 
                 // We didn't steal this value, forget it.
                 mem::forget(value);
+                counters.record(&Steal::Inconsistent::<T>);
 
                 if weak {
                     return Steal::Inconsistent;
@@ -435,8 +560,105 @@ impl<T> Worker<T> {
         self.deque.push(value);
     }
 
+    /// Pushes all elements from `iter` onto the bottom of the deque.
+    ///
+    /// If the number of elements is known in advance (as reported by the iterator's size hint),
+    /// the underlying buffer is grown only once, before any element gets pushed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the lower bound of the iterator's size hint makes the capacity overflow.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::deque;
+    ///
+    /// let (w, s) = deque::new();
+    /// w.push_iter(1..4);
+    ///
+    /// assert_eq!(w.pop(), Some(3));
+    /// assert_eq!(s.steal(), Some(1));
+    /// assert_eq!(w.pop(), Some(2));
+    /// ```
+    pub fn push_iter<I: IntoIterator<Item = T>>(&self, iter: I) {
+        let iter = iter.into_iter();
+        self.deque.reserve(iter.size_hint().0);
+
+        for value in iter {
+            self.deque.push(value);
+        }
+    }
+
+    /// Reserves capacity for at least `additional` more elements to be pushed.
+    ///
+    /// This is useful just before pushing a burst of elements, e.g. when a task spawns many child
+    /// tasks, because the buffer then grows at most once instead of doubling repeatedly. Stealers
+    /// may keep using the old buffer while it's being replaced, so it is destroyed only once they
+    /// are done with it.
+    ///
+    /// Note that popping elements may shrink the buffer again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity overflows `usize`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::deque;
+    ///
+    /// let (w, _) = deque::new();
+    /// w.reserve(1000);
+    /// for i in 0..1000 {
+    ///     w.push(i);
+    /// }
+    /// ```
+    pub fn reserve(&self, additional: usize) {
+        self.deque.reserve(additional);
+    }
+
+    /// Registers a `callback` that gets invoked whenever the deque's buffer grows.
+    ///
+    /// The callback receives the old and the new capacity, which is useful for detecting
+    /// unexpected reallocation churn. It runs on the worker's thread, right after the buffer has
+    /// been replaced. Registering a new callback replaces the previous one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::deque;
+    /// use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT};
+    /// use std::sync::atomic::Ordering::Relaxed;
+    ///
+    /// static GROWS: AtomicUsize = ATOMIC_USIZE_INIT;
+    ///
+    /// let (w, _) = deque::new();
+    /// w.on_grow(Box::new(|old, new| {
+    ///     assert!(new > old);
+    ///     GROWS.fetch_add(1, Relaxed);
+    /// }));
+    ///
+    /// for i in 0..1000 {
+    ///     w.push(i);
+    /// }
+    /// assert!(GROWS.load(Relaxed) > 0);
+    /// ```
+    pub fn on_grow(&self, callback: Box<Fn(usize, usize) + Send>) {
+        unsafe { *self.deque.on_grow.get() = Some(callback) }
+    }
+
     /// Pops an element from the bottom of the deque.
     ///
+    /// When a single element is left, the worker competes for it with stealers on equal terms:
+    /// exactly one of them gets it, so the element is never taken twice nor lost.
+    ///
+    /// If the deque was created with [`fifo`], the element is taken from the top instead, just
+    /// like with [`steal`].
+    ///
+    /// [`fifo`]: fn.fifo.html
+    /// [`steal`]: struct.Worker.html#method.steal
+    ///
     /// # Examples
     ///
     /// ```
@@ -451,7 +673,39 @@ impl<T> Worker<T> {
     /// assert_eq!(w.pop(), None);
     /// ```
     pub fn pop(&self) -> Option<T> {
-        self.deque.pop()
+        if self.deque.fifo {
+            self.steal()
+        } else {
+            self.deque.pop()
+        }
+    }
+
+    /// Pops all elements from the bottom of the deque and returns them in the order they were
+    /// popped.
+    ///
+    /// This is useful when shutting down a worker, to reclaim its remaining work. Stealers may
+    /// still be stealing elements at the same time: each element ends up either in the returned
+    /// vector or with exactly one stealer, never in both.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::deque;
+    ///
+    /// let (w, _) = deque::new();
+    /// w.push(1);
+    /// w.push(2);
+    /// w.push(3);
+    ///
+    /// assert_eq!(w.drain(), vec![3, 2, 1]);
+    /// assert_eq!(w.pop(), None);
+    /// ```
+    pub fn drain(&self) -> Vec<T> {
+        let mut drained = Vec::with_capacity(self.deque.len());
+        while let Some(value) = self.pop() {
+            drained.push(value);
+        }
+        drained
     }
 
     /// Steals an element from the top of the deque.
@@ -498,6 +752,61 @@ impl<T> Worker<T> {
     pub fn steal_weak(&self) -> Steal<T> {
         self.deque.steal_as_worker()
     }
+
+    /// Steals a batch of elements from `victim` and pushes them into this deque.
+    ///
+    /// Roughly half of the elements in the victim's deque are moved, so that the two deques end up
+    /// sharing the work. Returns [`Steal::Data`] if at least one element was moved, and otherwise
+    /// the outcome of the first failed steal.
+    ///
+    /// [`Steal::Data`]: enum.Steal.html#variant.Data
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::deque::{self, Steal};
+    ///
+    /// let (w1, _) = deque::new();
+    /// let (w2, s2) = deque::new();
+    /// for i in 0..10 {
+    ///     w2.push(i);
+    /// }
+    ///
+    /// assert_eq!(w1.steal_from(&s2), Steal::Data(()));
+    /// assert_eq!(w1.len(), 5);
+    /// assert_eq!(w1.pop(), Some(4));
+    /// ```
+    pub fn steal_from(&self, victim: &Stealer<T>) -> Steal<()> {
+        victim.steal_batch_fraction(self, 1, 2)
+    }
+}
+
+#[cfg(feature = "debug-internals")]
+impl<T> Worker<T> {
+    /// Returns the current top and bottom indices of the deque, in that order.
+    ///
+    /// Pushing increments the bottom and popping decrements it, except when popping the last
+    /// element, which increments the top instead. Stealing increments the top. All arithmetic on
+    /// the indices wraps around on overflow.
+    ///
+    /// This is only available with the `debug-internals` feature and is meant for debugging.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::deque;
+    ///
+    /// let (w, s) = deque::new();
+    /// w.push(1);
+    /// w.push(2);
+    /// s.steal();
+    /// assert_eq!(w.debug_indices(), (1, 2));
+    /// ```
+    pub fn debug_indices(&self) -> (isize, isize) {
+        let t = self.deque.top.load(Relaxed);
+        let b = self.deque.bottom.load(Relaxed);
+        (t, b)
+    }
 }
 
 impl<T> fmt::Debug for Worker<T> {
@@ -511,6 +820,7 @@ impl<T> fmt::Debug for Worker<T> {
 /// Stealers may be cloned in order to create more stealers for the same deque.
 pub struct Stealer<T> {
     deque: Arc<Deque<T>>,
+    counters: Counters,
     _marker: PhantomData<*mut ()>, // !Send + !Sync
 }
 
@@ -553,7 +863,7 @@ impl<T> Stealer<T> {
     /// assert_eq!(s.steal(), None);
     /// ```
     pub fn steal(&self) -> Option<T> {
-        match self.deque.steal(false) {
+        match self.deque.steal(false, &self.counters) {
             Steal::Empty => None,
             Steal::Data(data) => Some(data),
             Steal::Inconsistent => unreachable!(),
@@ -577,7 +887,165 @@ impl<T> Stealer<T> {
     ///
     /// [`Steal::Inconsistent`]: enum.Steal.html#variant.Inconsistent
     pub fn steal_weak(&self) -> Steal<T> {
-        self.deque.steal(true)
+        self.deque.steal(true, &self.counters)
+    }
+
+    /// Steals a fraction of the elements in the deque and pushes them into `dest`.
+    ///
+    /// About `numerator / denominator` of the elements are moved, rounding up, so at least one
+    /// element is moved if the deque isn't empty. Fractions greater than one are treated as one.
+    /// Returns [`Steal::Data`] if at least one element was moved, and otherwise the outcome of
+    /// the first failed steal.
+    ///
+    /// [`Worker::steal_from`] is a shortcut for stealing half of the elements. Schedulers can
+    /// steal smaller fractions to spread the work of a busy deque among more thieves.
+    ///
+    /// [`Steal::Data`]: enum.Steal.html#variant.Data
+    /// [`Worker::steal_from`]: struct.Worker.html#method.steal_from
+    ///
+    /// # Panics
+    ///
+    /// Panics if `denominator` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::deque::{self, Steal};
+    ///
+    /// let (w1, s1) = deque::new();
+    /// let (w2, _) = deque::new();
+    /// for i in 0..30 {
+    ///     w1.push(i);
+    /// }
+    ///
+    /// assert_eq!(s1.steal_batch_fraction(&w2, 1, 3), Steal::Data(()));
+    /// assert_eq!(w1.len(), 20);
+    /// assert_eq!(w2.len(), 10);
+    /// assert_eq!(w2.pop(), Some(9));
+    /// ```
+    pub fn steal_batch_fraction(
+        &self,
+        dest: &Worker<T>,
+        numerator: usize,
+        denominator: usize,
+    ) -> Steal<()> {
+        assert!(denominator > 0, "denominator must be non-zero");
+
+        // Round the fraction of the length up, but always make at least one attempt. A fraction
+        // that can't be computed without overflowing is large enough to take everything.
+        let len = self.len();
+        let numerator = cmp::min(numerator, denominator);
+        let count = match len.checked_mul(numerator) {
+            Some(n) => n / denominator + if n % denominator == 0 { 0 } else { 1 },
+            None => len,
+        };
+        let count = cmp::max(count, 1);
+
+        let mut result = Steal::Empty;
+        for _ in 0..count {
+            match self.steal_weak() {
+                Steal::Data(value) => {
+                    dest.push(value);
+                    result = Steal::Data(());
+                }
+                Steal::Empty => break,
+                Steal::Inconsistent => {
+                    if let Steal::Empty = result {
+                        result = Steal::Inconsistent;
+                    }
+                    break;
+                }
+            }
+        }
+        result
+    }
+
+    /// Returns an iterator that keeps stealing elements from the top of the deque.
+    ///
+    /// The iterator ends as soon as the deque is observed to be empty. Note that this doesn't
+    /// mean no more elements will ever come: the worker may push more elements later.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::deque;
+    ///
+    /// let (w, s) = deque::new();
+    /// w.push(1);
+    /// w.push(2);
+    /// w.push(3);
+    ///
+    /// assert_eq!(s.drain().collect::<Vec<_>>(), vec![1, 2, 3]);
+    /// assert_eq!(s.steal(), None);
+    /// ```
+    pub fn drain<'a>(&'a self) -> StealDrain<'a, T> {
+        StealDrain { stealer: self }
+    }
+
+    /// Converts the stealer into a worker if it is the last handle to the deque.
+    ///
+    /// This succeeds only if the worker and all other stealers have been dropped. The remaining
+    /// elements can then be drained with cheaper owner operations like [`Worker::pop`]. Otherwise,
+    /// the stealer is returned back as an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::deque;
+    ///
+    /// let (w, s) = deque::new();
+    /// w.push(1);
+    /// w.push(2);
+    ///
+    /// let s = s.try_into_worker().unwrap_err();
+    /// drop(w);
+    ///
+    /// let w = s.try_into_worker().unwrap();
+    /// assert_eq!(w.pop(), Some(2));
+    /// assert_eq!(w.pop(), Some(1));
+    /// ```
+    ///
+    /// [`Worker::pop`]: struct.Worker.html#method.pop
+    pub fn try_into_worker(mut self) -> Result<Worker<T>, Stealer<T>> {
+        // If this is the only reference to the deque, no other handle can be created anymore
+        // because the stealer is owned. Unlike checking the reference count, `Arc::get_mut` also
+        // synchronizes with the dropping of the other handles, so their writes are visible.
+        if Arc::get_mut(&mut self.deque).is_some() {
+            Ok(Worker {
+                deque: self.deque,
+                _marker: PhantomData,
+            })
+        } else {
+            Err(self)
+        }
+    }
+}
+
+#[cfg(feature = "stats")]
+impl<T> Stealer<T> {
+    /// Returns statistics on the steal operations performed by this stealer.
+    ///
+    /// Every stealer keeps its own statistics, so a cloned stealer starts from zero. Steals
+    /// performed by the worker are not counted.
+    ///
+    /// This is only available with the `stats` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::deque;
+    ///
+    /// let (w, s) = deque::new();
+    /// w.push(1);
+    /// s.steal();
+    /// s.steal();
+    ///
+    /// let stats = s.stats();
+    /// assert_eq!(stats.stolen, 1);
+    /// assert_eq!(stats.empty, 1);
+    /// ```
+    pub fn stats(&self) -> StealStats {
+        self.counters.stats()
     }
 }
 
@@ -585,6 +1053,7 @@ impl<T> Clone for Stealer<T> {
     fn clone(&self) -> Self {
         Stealer {
             deque: self.deque.clone(),
+            counters: Counters::default(),
             _marker: PhantomData,
         }
     }
@@ -596,52 +1065,501 @@ impl<T> fmt::Debug for Stealer<T> {
     }
 }
 
-/// Returns a new work-stealing deque.
+/// An iterator that steals elements from a deque until it becomes empty.
 ///
-/// The worker is unique, while stealers can be cloned and distributed among multiple threads.
+/// This `struct` is created by the [`drain`] method on [`Stealer`].
 ///
-/// The deque will be destructed as soon as it's worker and all it's stealers get dropped.
+/// [`drain`]: struct.Stealer.html#method.drain
+/// [`Stealer`]: struct.Stealer.html
+pub struct StealDrain<'a, T: 'a> {
+    stealer: &'a Stealer<T>,
+}
+
+impl<'a, T> Iterator for StealDrain<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.stealer.steal()
+    }
+}
+
+impl<'a, T> fmt::Debug for StealDrain<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "StealDrain {{ ... }}")
+    }
+}
+
+/// Steals from multiple deques in round-robin fashion.
+///
+/// Schedulers with many workers usually want to steal from any deque that has work. Each call to
+/// [`steal`] starts from a different victim than the previous one, so that the first victim doesn't
+/// get hammered by all the thieves.
+///
+/// [`steal`]: struct.MultiStealer.html#method.steal
 ///
 /// # Examples
 ///
 /// ```
-/// use coco::deque;
+/// use coco::deque::{self, MultiStealer, Steal};
 ///
-/// let (w, s1) = deque::new();
-/// let s2 = s1.clone();
-///
-/// w.push('a');
-/// w.push('b');
-/// w.push('c');
+/// let (w1, s1) = deque::new();
+/// let (w2, s2) = deque::new();
+/// w1.push(1);
+/// w2.push(2);
 ///
-/// assert_eq!(w.pop(), Some('c'));
-/// assert_eq!(s1.steal(), Some('a'));
-/// assert_eq!(s2.steal(), Some('b'));
+/// let s = MultiStealer::new(vec![s1, s2]);
+/// assert_eq!(s.steal(), Steal::Data(1));
+/// assert_eq!(s.steal(), Steal::Data(2));
+/// assert_eq!(s.steal(), Steal::Empty);
 /// ```
-pub fn new<T>() -> (Worker<T>, Stealer<T>) {
-    let d = Arc::new(Deque::new());
-    let worker = Worker {
-        deque: d.clone(),
-        _marker: PhantomData,
-    };
-    let stealer = Stealer {
-        deque: d,
-        _marker: PhantomData,
-    };
-    (worker, stealer)
+pub struct MultiStealer<T> {
+    /// The victims.
+    stealers: Vec<Stealer<T>>,
+    /// Index of the victim the next call to `steal` starts from.
+    next: AtomicUsize,
 }
 
-#[cfg(test)]
-mod tests {
-    extern crate rand;
+impl<T> MultiStealer<T> {
+    /// Returns a new multi-stealer for the specified victims.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::deque::{self, MultiStealer};
+    ///
+    /// let stealers = (0..4).map(|_| deque::new::<i32>().1).collect();
+    /// let s = MultiStealer::new(stealers);
+    /// ```
+    pub fn new(stealers: Vec<Stealer<T>>) -> Self {
+        MultiStealer {
+            stealers,
+            next: AtomicUsize::new(0),
+        }
+    }
 
-    use std::sync::{Arc, Mutex};
-    use std::sync::atomic::{AtomicBool, AtomicUsize};
-    use std::sync::atomic::Ordering::SeqCst;
+    /// Returns the victims.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::deque::{self, MultiStealer};
+    ///
+    /// let (_, s) = deque::new::<i32>();
+    /// let s = MultiStealer::new(vec![s]);
+    /// assert_eq!(s.stealers().len(), 1);
+    /// ```
+    pub fn stealers(&self) -> &[Stealer<T>] {
+        &self.stealers
+    }
+
+    /// Steals an element from one of the victims.
+    ///
+    /// Every victim is tried at most once, starting from a different one on each call. Returns
+    /// [`Steal::Empty`] if all victims were empty, or [`Steal::Inconsistent`] if no element was
+    /// stolen, but a concurrent operation got in the way on some victim, in which case a retry may
+    /// return more data.
+    ///
+    /// [`Steal::Empty`]: enum.Steal.html#variant.Empty
+    /// [`Steal::Inconsistent`]: enum.Steal.html#variant.Inconsistent
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::deque::{self, MultiStealer, Steal};
+    ///
+    /// let (w, s) = deque::new();
+    /// let s = MultiStealer::new(vec![s]);
+    ///
+    /// w.push(1);
+    /// assert_eq!(s.steal(), Steal::Data(1));
+    /// assert_eq!(s.steal(), Steal::Empty);
+    /// ```
+    pub fn steal(&self) -> Steal<T> {
+        let len = self.stealers.len();
+        let start = self.next.fetch_add(1, Relaxed);
+        let mut inconsistent = false;
+
+        for i in 0..len {
+            match self.stealers[start.wrapping_add(i) % len].steal_weak() {
+                Steal::Empty => {}
+                Steal::Data(data) => return Steal::Data(data),
+                Steal::Inconsistent => inconsistent = true,
+            }
+        }
+
+        if inconsistent {
+            Steal::Inconsistent
+        } else {
+            Steal::Empty
+        }
+    }
+}
+
+impl<T> fmt::Debug for MultiStealer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MultiStealer {{ ... }}")
+    }
+}
+
+/// A node in the linked list of an injector.
+struct InjectorNode<T> {
+    /// The payload. It is uninitialized in the dummy node at the head of the list.
+    value: MaybeUninit<T>,
+    /// The next node in the list.
+    next: Atomic<InjectorNode<T>>,
+}
+
+/// A global queue that tasks can be injected into by any thread.
+///
+/// Work-stealing schedulers usually pair per-thread deques with a shared injector. New tasks
+/// coming from outside the worker threads are pushed into the injector, and idle workers pull
+/// tasks from it in batches.
+///
+/// This is a lock-free Michael-Scott queue, so elements are stolen in the order they were pushed.
+///
+/// # Examples
+///
+/// ```
+/// use coco::deque::{self, Injector, Steal};
+///
+/// let inj = Injector::new();
+/// inj.push(1);
+/// inj.push(2);
+/// inj.push(3);
+///
+/// let (w, _) = deque::new();
+/// assert_eq!(inj.steal_batch(&w), Steal::Data(()));
+/// assert_eq!(w.steal(), Some(1));
+/// assert_eq!(w.steal(), Some(2));
+/// assert_eq!(w.steal(), Some(3));
+/// assert_eq!(inj.steal(), Steal::Empty);
+/// ```
+pub struct Injector<T> {
+    /// The dummy node at the front of the list.
+    head: Atomic<InjectorNode<T>>,
+
+    /// Some padding to avoid false sharing.
+    _pad0: [u8; CACHE_LINE_BYTES],
+
+    /// The last or second to last node in the list.
+    tail: Atomic<InjectorNode<T>>,
+
+    /// Some padding to avoid false sharing.
+    _pad1: [u8; CACHE_LINE_BYTES],
+}
+
+unsafe impl<T: Send> Send for Injector<T> {}
+unsafe impl<T: Send> Sync for Injector<T> {}
+
+impl<T> Injector<T> {
+    /// Maximum number of elements moved by a single call to `steal_batch`.
+    const MAX_BATCH: usize = 32;
+
+    /// Returns a new, empty injector.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::deque::Injector;
+    ///
+    /// let inj = Injector::<i32>::new();
+    /// ```
+    pub fn new() -> Self {
+        let dummy = Owned::new(InjectorNode {
+            value: MaybeUninit::uninit(),
+            next: Atomic::null(),
+        });
+
+        let inj = Injector {
+            head: Atomic::null(),
+            _pad0: [0; CACHE_LINE_BYTES],
+            tail: Atomic::null(),
+            _pad1: [0; CACHE_LINE_BYTES],
+        };
+        epoch::pin(|scope| {
+            let dummy = dummy.into_ptr(scope);
+            inj.head.store(dummy, Relaxed);
+            inj.tail.store(dummy, Relaxed);
+        });
+        inj
+    }
+
+    /// Returns `true` if the injector is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::deque::Injector;
+    ///
+    /// let inj = Injector::new();
+    /// assert!(inj.is_empty());
+    /// inj.push(1);
+    /// assert!(!inj.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        epoch::pin(|scope| {
+            let head = self.head.load(Acquire, scope);
+            unsafe { head.deref().next.load(Acquire, scope).is_null() }
+        })
+    }
+
+    /// Pushes an element into the injector.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::deque::Injector;
+    ///
+    /// let inj = Injector::new();
+    /// inj.push(1);
+    /// inj.push(2);
+    /// ```
+    pub fn push(&self, value: T) {
+        let mut node = Owned::new(InjectorNode {
+            value: MaybeUninit::new(value),
+            next: Atomic::null(),
+        });
+
+        epoch::pin(|scope| {
+            loop {
+                let tail = self.tail.load(Acquire, scope);
+                let t = unsafe { tail.deref() };
+                let next = t.next.load(Acquire, scope);
+
+                // If the tail is lagging behind, help moving it forward.
+                if !next.is_null() {
+                    let _ = self.tail.compare_and_swap(tail, next, AcqRel, scope);
+                    continue;
+                }
+
+                // Try appending the new node after the last one.
+                match t.next.compare_and_swap_weak_owned(next, node, AcqRel, scope) {
+                    Ok(new) => {
+                        // Try moving the tail to the new node. If this fails, someone else has
+                        // already moved it.
+                        let _ = self.tail.compare_and_swap(tail, new, AcqRel, scope);
+                        return;
+                    }
+                    Err((_, n)) => node = n,
+                }
+            }
+        })
+    }
+
+    /// Steals an element from the front of the injector.
+    ///
+    /// If another concurrent operation gets in the way when stealing data, this method will return
+    /// immediately with [`Steal::Inconsistent`] instead of retrying.
+    ///
+    /// [`Steal::Inconsistent`]: enum.Steal.html#variant.Inconsistent
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::deque::{Injector, Steal};
+    ///
+    /// let inj = Injector::new();
+    /// inj.push(1);
+    /// inj.push(2);
+    ///
+    /// assert_eq!(inj.steal(), Steal::Data(1));
+    /// assert_eq!(inj.steal(), Steal::Data(2));
+    /// assert_eq!(inj.steal(), Steal::Empty);
+    /// ```
+    pub fn steal(&self) -> Steal<T> {
+        epoch::pin(|scope| {
+            let head = self.head.load(Acquire, scope);
+            let h = unsafe { head.deref() };
+            let next = h.next.load(Acquire, scope);
+
+            let n = match unsafe { next.as_ref() } {
+                None => return Steal::Empty,
+                Some(n) => n,
+            };
+
+            // The tail must never fall behind the head, so move it forward if it's lagging.
+            let tail = self.tail.load(Acquire, scope);
+            if tail.as_raw() == head.as_raw() {
+                let _ = self.tail.compare_and_swap(tail, next, AcqRel, scope);
+            }
+
+            // Try moving the head forward. The next node becomes the new dummy node, and its value
+            // gets moved out by the thread that succeeds.
+            match self.head.compare_and_swap(head, next, AcqRel, scope) {
+                Ok(()) => unsafe {
+                    scope.defer_free(head);
+                    Steal::Data(ptr::read(n.value.as_ptr()))
+                },
+                Err(_) => Steal::Inconsistent,
+            }
+        })
+    }
+
+    /// Steals a batch of elements from the front of the injector and pushes them into `dest`.
+    ///
+    /// At most 32 elements are moved at once. Returns [`Steal::Data`] if at least one element was
+    /// moved, and otherwise the outcome of the first failed steal.
+    ///
+    /// [`Steal::Data`]: enum.Steal.html#variant.Data
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::deque::{self, Injector, Steal};
+    ///
+    /// let inj = Injector::new();
+    /// let (w, _) = deque::new();
+    /// assert_eq!(inj.steal_batch(&w), Steal::Empty);
+    ///
+    /// for i in 0..10 {
+    ///     inj.push(i);
+    /// }
+    /// assert_eq!(inj.steal_batch(&w), Steal::Data(()));
+    /// assert_eq!(w.len(), 10);
+    /// ```
+    pub fn steal_batch(&self, dest: &Worker<T>) -> Steal<()> {
+        let mut result = Steal::Empty;
+        for _ in 0..Self::MAX_BATCH {
+            match self.steal() {
+                Steal::Data(value) => {
+                    dest.push(value);
+                    result = Steal::Data(());
+                }
+                Steal::Empty => break,
+                Steal::Inconsistent => {
+                    if let Steal::Empty = result {
+                        result = Steal::Inconsistent;
+                    }
+                    break;
+                }
+            }
+        }
+        result
+    }
+}
+
+impl<T> Drop for Injector<T> {
+    fn drop(&mut self) {
+        unsafe {
+            epoch::unprotected(|scope| {
+                // Free the dummy node without dropping its uninitialized value.
+                let head = self.head.load(Relaxed, scope).as_raw() as *mut InjectorNode<T>;
+                let mut curr = (*head).next.load(Relaxed, scope).as_raw() as *mut InjectorNode<T>;
+                drop(Box::from_raw(head));
+
+                // Drop the values in the remaining nodes and free them.
+                while !curr.is_null() {
+                    let mut node = Box::from_raw(curr);
+                    ptr::drop_in_place(node.value.as_mut_ptr());
+                    curr = node.next.load(Relaxed, scope).as_raw() as *mut InjectorNode<T>;
+                }
+            })
+        }
+    }
+}
+
+impl<T> Default for Injector<T> {
+    fn default() -> Self {
+        Injector::new()
+    }
+}
+
+impl<T> fmt::Debug for Injector<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Injector {{ ... }}")
+    }
+}
+
+/// Returns a new work-stealing deque.
+///
+/// The worker is unique, while stealers can be cloned and distributed among multiple threads.
+///
+/// The deque will be destructed as soon as it's worker and all it's stealers get dropped.
+///
+/// # Examples
+///
+/// ```
+/// use coco::deque;
+///
+/// let (w, s1) = deque::new();
+/// let s2 = s1.clone();
+///
+/// w.push('a');
+/// w.push('b');
+/// w.push('c');
+///
+/// assert_eq!(w.pop(), Some('c'));
+/// assert_eq!(s1.steal(), Some('a'));
+/// assert_eq!(s2.steal(), Some('b'));
+/// ```
+pub fn new<T>() -> (Worker<T>, Stealer<T>) {
+    from_deque(Deque::new())
+}
+
+/// Returns a new work-stealing deque whose worker pops elements in FIFO order.
+///
+/// The worker still pushes onto the bottom, but [`Worker::pop`] takes from the top, the same end
+/// stealers steal from. The worker then behaves as a queue, which some schedulers prefer for
+/// fairness, at the cost of contending with stealers on every pop.
+///
+/// [`Worker::pop`]: struct.Worker.html#method.pop
+///
+/// # Examples
+///
+/// ```
+/// use coco::deque;
+///
+/// let (w, s) = deque::fifo();
+///
+/// w.push('a');
+/// w.push('b');
+/// w.push('c');
+///
+/// assert_eq!(w.pop(), Some('a'));
+/// assert_eq!(s.steal(), Some('b'));
+/// assert_eq!(w.pop(), Some('c'));
+/// ```
+pub fn fifo<T>() -> (Worker<T>, Stealer<T>) {
+    let mut d = Deque::new();
+    d.fifo = true;
+    from_deque(d)
+}
+
+/// Returns a worker and a stealer sharing the deque `d`.
+fn from_deque<T>(d: Deque<T>) -> (Worker<T>, Stealer<T>) {
+    let d = Arc::new(d);
+    let worker = Worker {
+        deque: d.clone(),
+        _marker: PhantomData,
+    };
+    let stealer = Stealer {
+        deque: d,
+        counters: Counters::default(),
+        _marker: PhantomData,
+    };
+    (worker, stealer)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+
+    use std::isize;
+    use std::iter;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{AtomicBool, AtomicUsize};
+    use std::sync::atomic::Ordering::{Relaxed, SeqCst};
     use std::thread;
 
     use epoch;
     use self::rand::Rng;
+    use super::{Deque, Injector, MultiStealer, Steal, Stealer, Worker};
+
+    /// Returns a new deque whose indices start at `index`.
+    fn new_at<T>(index: isize) -> (Worker<T>, Stealer<T>) {
+        super::from_deque(Deque::with_start_index(index))
+    }
 
     #[test]
     fn smoke() {
@@ -675,25 +1593,698 @@ mod tests {
     }
 
     #[test]
-    fn steal_push() {
-        const STEPS: usize = 50_000;
+    fn index_overflow() {
+        const COUNT: usize = 1000;
 
-        let (w, s) = super::new();
-        let t = thread::spawn(move || {
-            for i in 0..STEPS {
-                loop {
-                    if let Some(v) = s.steal() {
-                        assert_eq!(i, v);
-                        break;
+        let (w, s) = new_at(isize::MAX - 10);
+        for i in 0..COUNT {
+            w.push(i);
+        }
+        assert_eq!(w.len(), COUNT);
+
+        for i in 0..COUNT / 2 {
+            assert_eq!(s.steal(), Some(i));
+        }
+        for i in (COUNT / 2..COUNT).rev() {
+            assert_eq!(w.pop(), Some(i));
+        }
+        assert_eq!(w.pop(), None);
+        assert_eq!(s.steal(), None);
+        assert_eq!(w.len(), 0);
+
+        w.push(7);
+        assert_eq!(s.steal(), Some(7));
+        assert_eq!(w.pop(), None);
+    }
+
+    #[test]
+    fn index_overflow_stress() {
+        const COUNT: usize = 50_000;
+
+        let (w, s) = new_at(isize::MAX - COUNT as isize / 2);
+        let remaining = Arc::new(AtomicUsize::new(COUNT));
+
+        let threads = (0..8).map(|_| {
+            let s = s.clone();
+            let remaining = remaining.clone();
+
+            thread::spawn(move || {
+                let mut last = None;
+                while remaining.load(SeqCst) > 0 {
+                    if let Some(x) = s.steal() {
+                        assert!(last < Some(x));
+                        last = Some(x);
+                        remaining.fetch_sub(1, SeqCst);
                     }
                 }
-            }
-        });
+            })
+        }).collect::<Vec<_>>();
 
-        for i in 0..STEPS {
+        let mut rng = rand::thread_rng();
+        for i in 0..COUNT {
             w.push(i);
-        }
-        t.join().unwrap();
+            if rng.gen_range(0, 4) == 0 {
+                if w.pop().is_some() {
+                    remaining.fetch_sub(1, SeqCst);
+                }
+            }
+        }
+        while remaining.load(SeqCst) > 0 {
+            if w.pop().is_some() {
+                remaining.fetch_sub(1, SeqCst);
+            }
+        }
+
+        for t in threads {
+            t.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn push_iter() {
+        const COUNT: usize = 10_000;
+
+        let (w, s) = super::new();
+        w.push(0);
+        w.push_iter(1..COUNT);
+        assert_eq!(w.len(), COUNT);
+
+        for i in 0..COUNT / 2 {
+            assert_eq!(s.steal(), Some(i));
+        }
+        for i in (COUNT / 2..COUNT).rev() {
+            assert_eq!(w.pop(), Some(i));
+        }
+        assert_eq!(w.pop(), None);
+        assert_eq!(s.steal(), None);
+    }
+
+    #[test]
+    fn push_iter_overflow() {
+        let (w, s) = super::new();
+        w.push(1);
+
+        // The size hint alone makes the capacity overflow, so nothing is pushed.
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            w.push_iter(iter::repeat(2).take(usize::max_value()));
+        }));
+        assert!(res.is_err());
+
+        // The deque is left intact.
+        w.push(3);
+        assert_eq!(w.len(), 2);
+        assert_eq!(s.steal(), Some(1));
+        assert_eq!(w.pop(), Some(3));
+        assert_eq!(w.pop(), None);
+    }
+
+    #[test]
+    fn drain() {
+        const COUNT: usize = 50_000;
+
+        let (w, s) = super::new();
+        for i in 0..COUNT {
+            w.push(i);
+        }
+
+        let threads = (0..8).map(|_| {
+            let s = s.clone();
+            thread::spawn(move || s.drain().collect::<Vec<_>>())
+        }).collect::<Vec<_>>();
+
+        let mut all = Vec::new();
+        for t in threads {
+            let v = t.join().unwrap();
+            for w in v.windows(2) {
+                assert!(w[0] < w[1]);
+            }
+            all.extend(v);
+        }
+
+        all.sort();
+        assert_eq!(all, (0..COUNT).collect::<Vec<_>>());
+        assert_eq!(s.drain().next(), None);
+    }
+
+    #[test]
+    fn on_grow() {
+        let (w, _) = super::new();
+        let grows = Arc::new(Mutex::new(Vec::new()));
+
+        {
+            let grows = grows.clone();
+            w.on_grow(Box::new(move |old, new| grows.lock().unwrap().push((old, new))));
+        }
+
+        for i in 0..1000 {
+            w.push(i);
+        }
+        assert_eq!(
+            *grows.lock().unwrap(),
+            vec![(16, 32), (32, 64), (64, 128), (128, 256), (256, 512), (512, 1024)]
+        );
+
+        // Shrinking doesn't invoke the callback.
+        while w.pop().is_some() {}
+        assert_eq!(grows.lock().unwrap().len(), 6);
+
+        // Reserving grows the buffer at once, and only if needed.
+        w.reserve(1);
+        w.reserve(100_000);
+        assert_eq!(grows.lock().unwrap().len(), 7);
+        assert_eq!(grows.lock().unwrap().last(), Some(&(16, 131_072)));
+    }
+
+    #[test]
+    fn reserve() {
+        const COUNT: usize = 1000;
+
+        let (w, s) = super::new();
+        let buffer = || unsafe {
+            epoch::unprotected(|scope| w.deque.buffer.load(Relaxed, scope).as_raw() as usize)
+        };
+
+        w.push(0);
+        w.reserve(COUNT);
+        let reserved = buffer();
+
+        // Pushing the reserved number of elements doesn't grow the buffer again.
+        for i in 1..COUNT + 1 {
+            w.push(i);
+        }
+        assert_eq!(buffer(), reserved);
+
+        // Reserving what already fits is a no-op.
+        w.reserve(0);
+        assert_eq!(buffer(), reserved);
+
+        for i in 0..COUNT + 1 {
+            assert_eq!(s.steal(), Some(i));
+        }
+    }
+
+    #[test]
+    fn reserve_overflow() {
+        let (w, s) = super::new();
+        w.push(1);
+
+        for &additional in &[usize::max_value(), usize::max_value() - 1, isize::MAX as usize + 1] {
+            let res = panic::catch_unwind(AssertUnwindSafe(|| w.reserve(additional)));
+            assert!(res.is_err());
+        }
+
+        // The deque is left intact.
+        w.push(2);
+        assert_eq!(w.len(), 2);
+        assert_eq!(s.steal(), Some(1));
+        assert_eq!(w.pop(), Some(2));
+        assert_eq!(w.pop(), None);
+    }
+
+    #[test]
+    fn steal_from() {
+        let (w1, s1) = super::new();
+        let (w2, s2) = super::new();
+        assert_eq!(w1.steal_from(&s2), Steal::Empty);
+
+        for i in 0..100 {
+            w2.push(i);
+        }
+
+        // The empty worker refills itself with the oldest half of the victim's elements.
+        assert_eq!(w1.steal_from(&s2), Steal::Data(()));
+        assert_eq!(w1.len(), 50);
+        assert_eq!(w2.len(), 50);
+        for i in (0..50).rev() {
+            assert_eq!(w1.pop(), Some(i));
+        }
+        assert_eq!(w1.pop(), None);
+
+        // A single remaining element can be stolen too.
+        while w2.len() > 1 {
+            w2.pop();
+        }
+        assert_eq!(w1.steal_from(&s2), Steal::Data(()));
+        assert_eq!(w2.pop(), None);
+        assert_eq!(s1.steal(), Some(50));
+    }
+
+    #[test]
+    fn steal_batch_fraction() {
+        let (w1, s1) = super::new();
+        let (w2, _) = super::new();
+        assert_eq!(s1.steal_batch_fraction(&w2, 1, 3), Steal::Empty);
+
+        for i in 0..30 {
+            w1.push(i);
+        }
+
+        // A third of the elements is moved, the oldest ones first.
+        assert_eq!(s1.steal_batch_fraction(&w2, 1, 3), Steal::Data(()));
+        assert_eq!(w1.len(), 20);
+        assert_eq!(w2.len(), 10);
+        for i in (0..10).rev() {
+            assert_eq!(w2.pop(), Some(i));
+        }
+
+        // The count is rounded up: two thirds of 20 elements is 14 of them.
+        assert_eq!(s1.steal_batch_fraction(&w2, 2, 3), Steal::Data(()));
+        assert_eq!(w1.len(), 6);
+        assert_eq!(w2.len(), 14);
+
+        // A tiny fraction still moves one element.
+        assert_eq!(s1.steal_batch_fraction(&w2, 1, 100), Steal::Data(()));
+        assert_eq!(w1.len(), 5);
+        assert_eq!(s1.steal_batch_fraction(&w2, 0, 1), Steal::Data(()));
+        assert_eq!(w1.len(), 4);
+
+        // Fractions greater than one take everything.
+        assert_eq!(s1.steal_batch_fraction(&w2, 5, 2), Steal::Data(()));
+        assert_eq!(w1.len(), 0);
+        assert_eq!(w2.len(), 20);
+        assert_eq!(s1.steal_batch_fraction(&w2, usize::MAX, usize::MAX), Steal::Empty);
+    }
+
+    #[test]
+    fn worker_drain() {
+        const COUNT: usize = 50_000;
+
+        let (w, s) = super::new();
+        for i in 0..COUNT {
+            w.push(i);
+        }
+
+        let threads = (0..8).map(|_| {
+            let s = s.clone();
+            thread::spawn(move || s.drain().collect::<Vec<_>>())
+        }).collect::<Vec<_>>();
+
+        let mut all = w.drain();
+        for w in all.windows(2) {
+            assert!(w[0] > w[1]);
+        }
+
+        for t in threads {
+            all.extend(t.join().unwrap());
+        }
+
+        all.sort();
+        assert_eq!(all, (0..COUNT).collect::<Vec<_>>());
+        assert_eq!(w.drain(), vec![]);
+    }
+
+    #[test]
+    fn multi_stealer() {
+        const VICTIMS: usize = 4;
+        const COUNT: usize = 1000;
+
+        let (workers, stealers): (Vec<_>, Vec<_>) = (0..VICTIMS).map(|_| super::new()).unzip();
+        for (v, w) in workers.iter().enumerate() {
+            for i in 0..COUNT {
+                w.push((v, i));
+            }
+        }
+        let s = MultiStealer::new(stealers);
+
+        // Every victim should get hit once in each round.
+        let mut hits = [0; VICTIMS];
+        for _ in 0..VICTIMS {
+            match s.steal() {
+                Steal::Data((v, _)) => hits[v] += 1,
+                _ => panic!(),
+            }
+        }
+        assert_eq!(hits, [1; VICTIMS]);
+
+        let mut last = [0; VICTIMS];
+        loop {
+            match s.steal() {
+                Steal::Empty => break,
+                Steal::Data((v, i)) => {
+                    assert_eq!(last[v] + 1, i);
+                    last[v] = i;
+                }
+                Steal::Inconsistent => {}
+            }
+        }
+        assert_eq!(last, [COUNT - 1; VICTIMS]);
+
+        workers[2].push((2, COUNT));
+        assert_eq!(s.steal(), Steal::Data((2, COUNT)));
+        assert_eq!(s.steal(), Steal::Empty);
+    }
+
+    #[cfg(feature = "stats")]
+    #[test]
+    fn stats() {
+        const COUNT: usize = 50_000;
+
+        let (w, s) = super::new();
+        for i in 0..COUNT {
+            w.push(i);
+        }
+
+        let threads = (0..8).map(|_| {
+            let s = s.clone();
+            thread::spawn(move || {
+                let mut stolen = 0;
+                loop {
+                    match s.steal_weak() {
+                        Steal::Empty => break,
+                        Steal::Data(_) => stolen += 1,
+                        Steal::Inconsistent => {}
+                    }
+                }
+                (stolen, s.stats())
+            })
+        }).collect::<Vec<_>>();
+
+        let mut total = 0;
+        for t in threads {
+            let (stolen, stats) = t.join().unwrap();
+            assert_eq!(stats.stolen, stolen);
+            assert_eq!(stats.empty, 1);
+            total += stats.stolen;
+        }
+        assert_eq!(total, COUNT);
+        assert_eq!(s.stats(), Default::default());
+    }
+
+    #[cfg(feature = "debug-internals")]
+    #[test]
+    fn debug_indices() {
+        let (w, s) = super::new();
+        assert_eq!(w.debug_indices(), (0, 0));
+
+        for i in 0..100 {
+            w.push(i);
+        }
+        assert_eq!(w.debug_indices(), (0, 100));
+
+        w.pop();
+        s.steal();
+        w.steal();
+        assert_eq!(w.debug_indices(), (2, 99));
+
+        // Popping the last element increments the top instead of decrementing the bottom.
+        while w.pop().is_some() {}
+        assert_eq!(w.debug_indices(), (3, 3));
+
+        let (w, _) = new_at::<i32>(isize::MAX);
+        w.push(1);
+        assert_eq!(w.debug_indices(), (isize::MAX, isize::MIN));
+    }
+
+    #[test]
+    fn injector() {
+        let inj = Injector::new();
+        assert!(inj.is_empty());
+        assert_eq!(inj.steal(), Steal::Empty);
+
+        for i in 0..100 {
+            inj.push(i);
+        }
+        assert!(!inj.is_empty());
+        for i in 0..50 {
+            assert_eq!(inj.steal(), Steal::Data(i));
+        }
+
+        let (w, _) = super::new();
+        assert_eq!(inj.steal_batch(&w), Steal::Data(()));
+        assert_eq!(w.len(), 32);
+        for i in 50..82 {
+            assert_eq!(w.steal(), Some(i));
+        }
+    }
+
+    #[test]
+    fn injector_destructors() {
+        struct Elem(Arc<AtomicUsize>);
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, SeqCst);
+            }
+        }
+
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let inj = Injector::new();
+        for _ in 0..100 {
+            inj.push(Elem(dropped.clone()));
+        }
+        for _ in 0..30 {
+            match inj.steal() {
+                Steal::Data(e) => drop(e),
+                _ => panic!(),
+            }
+        }
+        assert_eq!(dropped.load(SeqCst), 30);
+
+        drop(inj);
+        assert_eq!(dropped.load(SeqCst), 100);
+    }
+
+    #[test]
+    fn injector_stress() {
+        const PRODUCERS: usize = 2;
+        const CONSUMERS: usize = 4;
+        const COUNT: usize = 20_000;
+
+        let inj = Arc::new(Injector::new());
+        let hits = (0..PRODUCERS * COUNT).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>();
+        let hits = Arc::new(hits);
+        let remaining = Arc::new(AtomicUsize::new(PRODUCERS * COUNT));
+        let (tx, rx) = ::std::sync::mpsc::channel();
+
+        let producers = (0..PRODUCERS).map(|p| {
+            let inj = inj.clone();
+            thread::spawn(move || {
+                for i in 0..COUNT {
+                    inj.push(p * COUNT + i);
+                }
+            })
+        }).collect::<Vec<_>>();
+
+        // Each consumer pulls batches from the injector into its own deque and processes them.
+        let consumers = (0..CONSUMERS).map(|_| {
+            let inj = inj.clone();
+            let hits = hits.clone();
+            let remaining = remaining.clone();
+            let tx = tx.clone();
+
+            thread::spawn(move || {
+                let (w, s) = super::new::<usize>();
+                tx.send(s).unwrap();
+
+                while remaining.load(SeqCst) > 0 {
+                    match w.pop() {
+                        Some(x) => {
+                            hits[x].fetch_add(1, SeqCst);
+                            remaining.fetch_sub(1, SeqCst);
+                        }
+                        None => {
+                            inj.steal_batch(&w);
+                            thread::yield_now();
+                        }
+                    }
+                }
+            })
+        }).collect::<Vec<_>>();
+
+        // A thief steals from the consumers' deques.
+        let stealers = (0..CONSUMERS).map(|_| rx.recv().unwrap()).collect::<Vec<_>>();
+        let thief = {
+            let hits = hits.clone();
+            let remaining = remaining.clone();
+            thread::spawn(move || {
+                let s = MultiStealer::new(stealers);
+                while remaining.load(SeqCst) > 0 {
+                    match s.steal() {
+                        Steal::Data(x) => {
+                            hits[x].fetch_add(1, SeqCst);
+                            remaining.fetch_sub(1, SeqCst);
+                        }
+                        _ => thread::yield_now(),
+                    }
+                }
+            })
+        };
+
+        for t in producers.into_iter().chain(consumers).chain(Some(thief)) {
+            t.join().unwrap();
+        }
+
+        assert!(hits.iter().all(|h| h.load(SeqCst) == 1));
+        assert!(inj.is_empty());
+    }
+
+    #[test]
+    fn steal_push() {
+        const STEPS: usize = 50_000;
+
+        let (w, s) = super::new();
+        let t = thread::spawn(move || {
+            for i in 0..STEPS {
+                loop {
+                    if let Some(v) = s.steal() {
+                        assert_eq!(i, v);
+                        break;
+                    }
+                }
+            }
+        });
+
+        for i in 0..STEPS {
+            w.push(i);
+        }
+        t.join().unwrap();
+    }
+
+    #[test]
+    fn fifo() {
+        let (lifo_w, lifo_s) = super::new();
+        let (fifo_w, fifo_s) = super::fifo();
+        for i in 0..10 {
+            lifo_w.push(i);
+            fifo_w.push(i);
+        }
+
+        // Stealers take from the top in both modes.
+        assert_eq!(lifo_s.steal(), Some(0));
+        assert_eq!(fifo_s.steal(), Some(0));
+
+        for i in 1..10 {
+            assert_eq!(lifo_w.pop(), Some(10 - i));
+            assert_eq!(fifo_w.pop(), Some(i));
+        }
+        assert_eq!(lifo_w.pop(), None);
+        assert_eq!(fifo_w.pop(), None);
+
+        for i in 0..5 {
+            fifo_w.push(i);
+        }
+        assert_eq!(fifo_w.drain(), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn fifo_stress() {
+        const COUNT: usize = 50_000;
+        const THREADS: usize = 2;
+
+        let (w, s) = super::fifo::<usize>();
+        let done = Arc::new(AtomicBool::new(false));
+        let hits = Arc::new((0..COUNT).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>());
+
+        let threads = (0..THREADS).map(|_| {
+            let s = s.clone();
+            let done = done.clone();
+            let hits = hits.clone();
+
+            thread::spawn(move || {
+                while !done.load(SeqCst) {
+                    match s.steal() {
+                        Some(i) => {
+                            hits[i].fetch_add(1, SeqCst);
+                        }
+                        None => thread::yield_now(),
+                    }
+                }
+            })
+        }).collect::<Vec<_>>();
+
+        let mut last = None;
+        for i in 0..COUNT {
+            w.push(i);
+            if i % 2 == 0 {
+                if let Some(x) = w.pop() {
+                    // The worker pops in increasing order.
+                    if let Some(l) = last {
+                        assert!(l < x);
+                    }
+                    last = Some(x);
+                    hits[x].fetch_add(1, SeqCst);
+                }
+            }
+        }
+        while w.len() > 0 {
+            thread::yield_now();
+        }
+        done.store(true, SeqCst);
+        for t in threads {
+            t.join().unwrap();
+        }
+        while let Some(x) = w.pop() {
+            hits[x].fetch_add(1, SeqCst);
+        }
+
+        assert!(hits.iter().all(|h| h.load(SeqCst) == 1));
+    }
+
+    #[test]
+    fn try_into_worker() {
+        let (w, s) = super::new();
+        for i in 0..10 {
+            w.push(i);
+        }
+
+        let s2 = s.clone();
+        let s = s.try_into_worker().unwrap_err();
+        drop(w);
+        let s = s.try_into_worker().unwrap_err();
+        drop(s2);
+
+        let w = s.try_into_worker().unwrap();
+        for i in (0..10).rev() {
+            assert_eq!(w.pop(), Some(i));
+        }
+        assert_eq!(w.pop(), None);
+
+        // The converted worker can keep using the deque as usual.
+        w.push(10);
+        assert_eq!(w.pop(), Some(10));
+    }
+
+    #[test]
+    fn pop_last_race() {
+        const STEPS: usize = 50_000;
+        const THREADS: usize = 2;
+
+        let (w, s) = super::new::<usize>();
+        let done = Arc::new(AtomicBool::new(false));
+        let hits = Arc::new((0..STEPS).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>());
+
+        let threads = (0..THREADS).map(|_| {
+            let s = s.clone();
+            let done = done.clone();
+            let hits = hits.clone();
+
+            thread::spawn(move || {
+                while !done.load(SeqCst) {
+                    if let Steal::Data(i) = s.steal_weak() {
+                        hits[i].fetch_add(1, SeqCst);
+                    }
+                }
+            })
+        }).collect::<Vec<_>>();
+
+        // The deque never holds more than one element, so every pop contends for the last one.
+        for i in 0..STEPS {
+            w.push(i);
+            if let Some(i) = w.pop() {
+                hits[i].fetch_add(1, SeqCst);
+            }
+            if i % 100 == 0 {
+                thread::yield_now();
+            }
+        }
+
+        done.store(true, SeqCst);
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        assert!(hits.iter().all(|h| h.load(SeqCst) == 1));
+        assert_eq!(w.pop(), None);
     }
 
     #[test]
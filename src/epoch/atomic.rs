@@ -1,5 +1,6 @@
+use std::fmt;
 use std::marker::PhantomData;
-use std::mem;
+use std::mem::{self, MaybeUninit};
 use std::ops::{Deref, DerefMut};
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
@@ -49,7 +50,6 @@ fn data_with_tag<T>(data: usize, tag: usize) -> usize {
 /// Any method that loads the pointer must be passed a reference to a [`Scope`].
 ///
 /// [`Scope`]: struct.Scope.html
-#[derive(Debug)]
 pub struct Atomic<T> {
     data: AtomicUsize,
     _marker: PhantomData<*mut T>,
@@ -81,14 +81,18 @@ impl<T> Atomic<T> {
 
     /// Returns a new null atomic pointer.
     ///
+    /// This is a `const fn`, so it can be used to initialize statics.
+    ///
     /// # Examples
     ///
     /// ```
     /// use coco::epoch::Atomic;
     ///
     /// let a = Atomic::<i32>::null();
+    ///
+    /// static HEAD: Atomic<i32> = Atomic::null();
     /// ```
-    pub fn null() -> Self {
+    pub const fn null() -> Self {
         Atomic {
             data: AtomicUsize::new(0),
             _marker: PhantomData,
@@ -110,6 +114,9 @@ impl<T> Atomic<T> {
 
     /// Returns a new atomic pointer pointing to `owned`.
     ///
+    /// The atomic isn't shared with other threads yet, so no atomic operation is needed to
+    /// initialize it.
+    ///
     /// # Examples
     ///
     /// ```
@@ -158,6 +165,48 @@ impl<T> Atomic<T> {
         Ptr::from_data(self.data.load(ord))
     }
 
+    /// Loads the atomic pointer and converts it to a reference.
+    ///
+    /// This is a shortcut for `load(ord, scope).as_ref()`. The returned reference is tied to the
+    /// lifetime of `scope`, so the borrow checker makes sure it doesn't outlive the pinning.
+    ///
+    /// # Safety
+    ///
+    /// The same conditions apply as for [`Ptr::as_ref`]: the object must not be destroyed while
+    /// `'scope` is alive, and its initialization must be synchronized with this load.
+    ///
+    /// [`Ptr::as_ref`]: struct.Ptr.html#method.as_ref
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::epoch::{self, Atomic};
+    /// use std::sync::atomic::Ordering::SeqCst;
+    ///
+    /// let a = Atomic::new((1, 2));
+    /// epoch::pin(|scope| unsafe {
+    ///     let r = a.load_ref(SeqCst, scope).unwrap();
+    ///     assert_eq!(r.1, 2);
+    /// });
+    /// ```
+    ///
+    /// The reference cannot escape the scope:
+    ///
+    /// ```compile_fail
+    /// use coco::epoch::{self, Atomic};
+    /// use std::sync::atomic::Ordering::SeqCst;
+    ///
+    /// let a = Atomic::new(1234);
+    /// let r = epoch::pin(|scope| unsafe { a.load_ref(SeqCst, scope) });
+    /// ```
+    pub unsafe fn load_ref<'scope>(
+        &self,
+        ord: Ordering,
+        scope: &'scope Scope,
+    ) -> Option<&'scope T> {
+        self.load(ord, scope).as_ref()
+    }
+
     /// Stores a `Ptr` into the atomic pointer.
     ///
     /// This method takes an [`Ordering`] argument which describes the memory ordering of this
@@ -222,6 +271,42 @@ impl<T> Atomic<T> {
         Ptr::from_data(self.data.swap(new.data, ord))
     }
 
+    /// Stores an `Owned` into the atomic pointer, returning the previous `Ptr`.
+    ///
+    /// Ownership of `new` is transferred to the atomic pointer, while the ownership of the
+    /// previous object is transferred to the caller. Other threads may still be reading the
+    /// previous object, so it should be destroyed by passing it to [`defer_free`] or
+    /// [`defer_drop`].
+    ///
+    /// This method takes an [`Ordering`] argument which describes the memory ordering of this
+    /// operation.
+    ///
+    /// [`defer_free`]: struct.Scope.html#method.defer_free
+    /// [`defer_drop`]: struct.Scope.html#method.defer_drop
+    /// [`Ordering`]: https://doc.rust-lang.org/std/sync/atomic/enum.Ordering.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::epoch::{self, Atomic, Owned};
+    /// use std::sync::atomic::Ordering::SeqCst;
+    ///
+    /// let a = Atomic::new(1234);
+    /// epoch::pin(|scope| {
+    ///     let p = a.swap_owned(Owned::new(5678), SeqCst, scope);
+    ///     unsafe {
+    ///         assert_eq!(p.as_ref(), Some(&1234));
+    ///         scope.defer_free(p);
+    ///     }
+    /// });
+    /// ```
+    pub fn swap_owned<'scope>(&self, new: Owned<T>, ord: Ordering, _: &'scope Scope)
+                              -> Ptr<'scope, T> {
+        let data = new.data;
+        mem::forget(new);
+        Ptr::from_data(self.data.swap(data, ord))
+    }
+
     /// Stores `new` into the atomic pointer if the current value is the same as `current`.
     ///
     /// The return value is a result indicating whether the new pointer was written. On failure the
@@ -403,11 +488,73 @@ impl<T> Atomic<T> {
         }
     }
 
+    /// Fetches the pointer and applies `f` to it, storing the result if `f` returns `Some`.
+    ///
+    /// This is the common CAS loop wrapped up in a method: `f` computes the new pointer from the
+    /// current one, and if another thread changes the atomic in the meantime, `f` is called again
+    /// with the new current pointer. Returns `Ok(previous)` once the new pointer is written, or
+    /// `Err(previous)` if `f` returns `None`. This mirrors `fetch_update` on the atomic types in
+    /// the standard library.
+    ///
+    /// Since `f` may be called more than once, it shouldn't allocate a new object every time it
+    /// is called, or the objects from failed attempts will leak.
+    ///
+    /// This method takes two [`Ordering`] arguments: `set_ord` for when the new pointer is
+    /// written, and `fetch_ord` for the loads. They have the same meaning as the success and
+    /// failure orderings of `compare_exchange` in the standard library.
+    ///
+    /// [`Ordering`]: https://doc.rust-lang.org/std/sync/atomic/enum.Ordering.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::epoch::{self, Atomic, Ptr};
+    /// use std::sync::atomic::Ordering::SeqCst;
+    ///
+    /// let a = Atomic::<i32>::from_ptr(Ptr::null());
+    ///
+    /// epoch::pin(|scope| {
+    ///     // Set the tag to 1, but only if it isn't set already.
+    ///     let prev = a.fetch_update(SeqCst, SeqCst, scope, |p| {
+    ///         if p.tag() == 0 { Some(p.with_tag(1)) } else { None }
+    ///     });
+    ///     assert_eq!(prev.unwrap().tag(), 0);
+    ///
+    ///     let prev = a.fetch_update(SeqCst, SeqCst, scope, |p| {
+    ///         if p.tag() == 0 { Some(p.with_tag(1)) } else { None }
+    ///     });
+    ///     assert_eq!(prev.unwrap_err().tag(), 1);
+    /// });
+    /// ```
+    pub fn fetch_update<'scope, F>(
+        &self,
+        set_ord: Ordering,
+        fetch_ord: Ordering,
+        _: &'scope Scope,
+        mut f: F,
+    ) -> Result<Ptr<'scope, T>, Ptr<'scope, T>>
+    where
+        F: FnMut(Ptr<'scope, T>) -> Option<Ptr<'scope, T>>,
+    {
+        let mut prev = Ptr::from_data(self.data.load(fetch_ord));
+        while let Some(next) = f(prev) {
+            match self.data.compare_exchange_weak(prev.data, next.data, set_ord, fetch_ord) {
+                Ok(_) => return Ok(prev),
+                Err(current) => prev = Ptr::from_data(current),
+            }
+        }
+        Err(prev)
+    }
+
     /// Bitwise "and" with the current tag.
     ///
     /// Performs a bitwise "and" operation on the current tag and the argument `val`, and sets the
     /// new tag to the result. Returns the previous pointer.
     ///
+    /// Only the tag bits are affected, the pointer itself is left intact. This makes the method
+    /// suitable for clearing the mark that flags a node as logically deleted, as is common in
+    /// lock-free linked lists.
+    ///
     /// This method takes an [`Ordering`] argument which describes the memory ordering of this
     /// operation.
     ///
@@ -434,6 +581,9 @@ impl<T> Atomic<T> {
     /// Performs a bitwise "or" operation on the current tag and the argument `val`, and sets the
     /// new tag to the result. Returns the previous pointer.
     ///
+    /// Only the tag bits are affected, the pointer itself is left intact. This makes the method
+    /// suitable for marking a node as logically deleted, as is common in lock-free linked lists.
+    ///
     /// This method takes an [`Ordering`] argument which describes the memory ordering of this
     /// operation.
     ///
@@ -482,6 +632,31 @@ impl<T> Atomic<T> {
     }
 }
 
+/// Prints the address and the tag of the current pointer, which is loaded with relaxed ordering.
+///
+/// The pointer is never dereferenced, so this is safe even if the object has been destroyed.
+///
+/// # Examples
+///
+/// ```
+/// use coco::epoch::{Atomic, Ptr};
+///
+/// let a = Atomic::<i32>::from_ptr(Ptr::null().with_tag(1));
+/// assert_eq!(format!("{:?}", a), "Atomic { raw: 0x0, tag: 1 }");
+/// ```
+impl<T> fmt::Debug for Atomic<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let data = self.data.load(Ordering::Relaxed);
+        let raw = (data & !low_bits::<T>()) as *const T;
+        let tag = data & low_bits::<T>();
+
+        f.debug_struct("Atomic")
+            .field("raw", &raw)
+            .field("tag", &tag)
+            .finish()
+    }
+}
+
 impl<T> Default for Atomic<T> {
     fn default() -> Self {
         Atomic::null()
@@ -577,6 +752,24 @@ impl<T> Owned<T> {
         Ptr::from_data(data)
     }
 
+    /// Converts the owned pointer into a `Box`.
+    ///
+    /// The tag stored within the pointer is lost.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::epoch::Owned;
+    ///
+    /// let o = Owned::new(1234).with_tag(1);
+    /// assert_eq!(*o.into_box(), 1234);
+    /// ```
+    pub fn into_box(self) -> Box<T> {
+        let raw = (self.data & !low_bits::<T>()) as *mut T;
+        mem::forget(self);
+        unsafe { Box::from_raw(raw) }
+    }
+
     /// Returns the tag stored within the pointer.
     ///
     /// # Examples
@@ -607,6 +800,62 @@ impl<T> Owned<T> {
         mem::forget(self);
         Self::from_data(data_with_tag::<T>(data, tag))
     }
+
+    /// Allocates uninitialized memory for a `T` on the heap and returns an owned pointer to it.
+    ///
+    /// The value can be written in place later, after which the pointer is converted with
+    /// [`assume_init`]. Dropping the pointer before that frees the memory without running any
+    /// destructor.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::epoch::Owned;
+    ///
+    /// let mut o = Owned::<u32>::new_uninit();
+    /// o.write(1234);
+    /// let o = unsafe { o.assume_init() };
+    /// assert_eq!(*o, 1234);
+    /// ```
+    ///
+    /// [`assume_init`]: struct.Owned.html#method.assume_init
+    pub fn new_uninit() -> Owned<MaybeUninit<T>> {
+        Owned::new(MaybeUninit::uninit())
+    }
+}
+
+impl<T> Owned<MaybeUninit<T>> {
+    /// Converts the owned pointer to uninitialized memory into an owned pointer to a `T`.
+    ///
+    /// The tag stored within the pointer is preserved.
+    ///
+    /// # Safety
+    ///
+    /// The value must be fully initialized before calling this method. It is undefined behavior
+    /// to convert the pointer while the memory is still uninitialized, even if the value is never
+    /// read afterwards, because dropping the returned pointer will run `T`'s destructor.
+    ///
+    /// Other threads must not access the memory through a [`Ptr`] until it has been initialized
+    /// and the initialization has been published with an appropriate memory ordering.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::epoch::Owned;
+    ///
+    /// let mut o = Owned::<String>::new_uninit().with_tag(1);
+    /// o.write("hello".to_string());
+    /// let o = unsafe { o.assume_init() };
+    /// assert_eq!(*o, "hello");
+    /// assert_eq!(o.tag(), 1);
+    /// ```
+    ///
+    /// [`Ptr`]: struct.Ptr.html
+    pub unsafe fn assume_init(self) -> Owned<T> {
+        let data = self.data;
+        mem::forget(self);
+        Owned::from_data(data)
+    }
 }
 
 impl<T> Drop for Owned<T> {
@@ -638,6 +887,25 @@ impl<T> DerefMut for Owned<T> {
 ///
 /// The pointer must be properly aligned. Since it is aligned, a tag can be stored into the unused
 /// least significant bits of the address.
+///
+/// # Ownership
+///
+/// Unlike [`Owned`], a `Ptr` is a non-owning, shared reference: any number of them may point to
+/// the same object, they can be freely copied, and dropping one never destroys the object. This
+/// makes `Ptr` the right type for referring to nodes that are owned by a data structure, or shared
+/// between several [`Atomic`]s, as in a graph.
+///
+/// A `Ptr` is obtained by loading an [`Atomic`], or by turning an [`Owned`] into one with
+/// [`Owned::into_ptr`], which gives up ownership. The object is destroyed only when someone who
+/// has unlinked it from every [`Atomic`] passes the pointer to [`Scope::defer_free`] or
+/// [`Scope::defer_drop`]. Even then, destruction waits until every thread that was pinned at that
+/// moment gets unpinned, so a `Ptr` stays safe to dereference for as long as its `'scope` lasts.
+///
+/// [`Owned`]: struct.Owned.html
+/// [`Atomic`]: struct.Atomic.html
+/// [`Owned::into_ptr`]: struct.Owned.html#method.into_ptr
+/// [`Scope::defer_free`]: struct.Scope.html#method.defer_free
+/// [`Scope::defer_drop`]: struct.Scope.html#method.defer_drop
 #[derive(Debug)]
 pub struct Ptr<'scope, T: 'scope> {
     data: usize,
@@ -751,24 +1019,33 @@ impl<'scope, T> Ptr<'scope, T> {
     ///
     /// Returns a reference to the pointee that is valid in `'scope`.
     ///
+    /// This is a shortcut for `as_ref().unwrap()` without the null check, useful in hot paths where
+    /// the pointer is already known to be non-null.
+    ///
     /// # Safety
     ///
-    /// Dereferencing a pointer to an invalid object is not a concern, since invalid `Ptr`s
-    /// can only be constructed via other unsafe functions.
+    /// The caller must make sure that:
     ///
-    /// However, this method doesn't check whether the pointer is null, so dereferencing a null
-    /// pointer is unsafe.
+    /// * The pointer is not null. Use [`as_ref`] if it might be.
+    /// * The object is not destroyed while `'scope` is alive. This holds for objects loaded from
+    ///   an [`Atomic`] if they only ever get destroyed through [`defer_free`] or [`defer_drop`]
+    ///   after being unlinked, but not if some thread frees them immediately.
+    /// * Initialization of the object is synchronized with the read from the current thread.
     ///
-    /// Another source of unsafety is the possibility of unsynchronized reads to the objects.
-    /// For example, the following scenario is unsafe:
+    /// The last point deserves an example. The following scenario is unsafe:
     ///
     /// * A thread stores a new object: `a.store_owned(Owned::new(10), Relaxed)`
-    /// * Another thread reads it: `*a.load(Relaxed, scope).as_ref().unwrap()`
+    /// * Another thread reads it: `*a.load(Relaxed, scope).deref()`
     ///
     /// The problem is that relaxed orderings don't synchronize initialization of the object with
     /// the read from the second thread. This is a data race. A possible solution would be to use
     /// `Release` and `Acquire` orderings (or stronger).
     ///
+    /// [`as_ref`]: struct.Ptr.html#method.as_ref
+    /// [`Atomic`]: struct.Atomic.html
+    /// [`defer_free`]: struct.Scope.html#method.defer_free
+    /// [`defer_drop`]: struct.Scope.html#method.defer_drop
+    ///
     /// # Examples
     ///
     /// ```
@@ -778,8 +1055,10 @@ impl<'scope, T> Ptr<'scope, T> {
     /// let a = Atomic::new(1234);
     /// epoch::pin(|scope| {
     ///     let p = a.load(SeqCst, scope);
-    ///     unsafe {
-    ///         assert_eq!(p.deref(), &1234);
+    ///     if !p.is_null() {
+    ///         unsafe {
+    ///             assert_eq!(p.deref(), &1234);
+    ///         }
     ///     }
     /// });
     /// ```
@@ -793,11 +1072,11 @@ impl<'scope, T> Ptr<'scope, T> {
     ///
     /// # Safety
     ///
-    /// This method checks whether the pointer is null, and if not, assumes that it's pointing to a
-    /// valid object. However, this is not considered a source of unsafety because invalid `Ptr`s
-    /// can only be constructed via other unsafe functions.
+    /// This method checks whether the pointer is null. Other than that, the same conditions apply
+    /// as for [`deref`]: the object must not be destroyed while `'scope` is alive, and its
+    /// initialization must be synchronized with the read from the current thread.
     ///
-    /// The only source of unsafety is the possibility of unsynchronized reads to the objects.
+    /// The most common mistake is an unsynchronized read of the object.
     /// For example, the following scenario is unsafe:
     ///
     /// * A thread stores a new object: `a.store_owned(Owned::new(10), Relaxed)`
@@ -807,6 +1086,8 @@ impl<'scope, T> Ptr<'scope, T> {
     /// the read from the second thread. This is a data race. A possible solution would be to use
     /// `Release` and `Acquire` orderings (or stronger).
     ///
+    /// [`deref`]: struct.Ptr.html#method.deref
+    ///
     /// # Examples
     ///
     /// ```
@@ -871,3 +1152,306 @@ impl<'scope, T> Default for Ptr<'scope, T> {
         Ptr::null()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT};
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::thread;
+
+    use epoch::{self, Atomic, Owned, Ptr};
+
+    #[test]
+    fn null() {
+        static A: Atomic<i32> = Atomic::null();
+        epoch::pin(|scope| assert!(A.load(SeqCst, scope).is_null()));
+    }
+
+    #[test]
+    fn debug() {
+        let a = Atomic::<u64>::null();
+        assert_eq!(format!("{:?}", a), "Atomic { raw: 0x0, tag: 0 }");
+
+        let a = Atomic::from_owned(Owned::new(7u64).with_tag(3));
+        epoch::pin(|scope| unsafe {
+            let p = a.load(SeqCst, scope);
+            let expected = format!("Atomic {{ raw: {:p}, tag: 3 }}", p.as_raw());
+            assert_eq!(format!("{:?}", a), expected);
+            assert!(expected.contains(&format!("{:x}", p.as_raw() as usize)));
+            scope.defer_free(p);
+        });
+    }
+
+    #[test]
+    fn from_owned() {
+        let o = Owned::new(7).with_tag(1);
+        let raw = &*o as *const i32;
+        let a = Atomic::from_owned(o);
+
+        epoch::pin(|scope| unsafe {
+            let p = a.load(SeqCst, scope);
+            assert_eq!(p.as_raw(), raw);
+            assert_eq!(p.tag(), 1);
+            assert_eq!(*p.deref(), 7);
+            scope.defer_free(p);
+        });
+    }
+
+    #[test]
+    fn deref() {
+        let a = Atomic::new(7);
+        epoch::pin(|scope| unsafe {
+            let p = a.load(SeqCst, scope);
+            assert_eq!(*p.deref(), 7);
+            assert_eq!(*p.with_tag(1).deref(), 7);
+            scope.defer_free(p);
+        });
+    }
+
+    #[test]
+    fn as_ref() {
+        let a = Atomic::new(7);
+        epoch::pin(|scope| unsafe {
+            let p = a.load(SeqCst, scope);
+            assert_eq!(p.as_ref(), Some(&7));
+            assert_eq!(Ptr::<i32>::null().as_ref(), None);
+            assert_eq!(Ptr::<i32>::null().with_tag(1).as_ref(), None);
+            scope.defer_free(p);
+        });
+    }
+
+    #[test]
+    fn swap_owned() {
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Elem(i32);
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, SeqCst);
+            }
+        }
+
+        let a = Atomic::new(Elem(1));
+        epoch::pin(|scope| unsafe {
+            let old = a.swap_owned(Owned::new(Elem(2)), SeqCst, scope);
+            assert_eq!(old.deref().0, 1);
+            assert_eq!(a.load(SeqCst, scope).deref().0, 2);
+            scope.defer_drop(old);
+        });
+
+        // Threads from other tests may be pinned and hold back the epoch for a little while.
+        while DROPS.load(SeqCst) < 1 {
+            epoch::force_reclaim_all();
+            thread::yield_now();
+        }
+        assert_eq!(DROPS.load(SeqCst), 1);
+
+        // Reclaiming again doesn't destroy the old value twice, and the new value is intact.
+        epoch::force_reclaim_all();
+        assert_eq!(DROPS.load(SeqCst), 1);
+        epoch::pin(|scope| unsafe {
+            assert_eq!(a.load(SeqCst, scope).deref().0, 2);
+            scope.defer_drop(a.swap(Ptr::null(), SeqCst, scope));
+        });
+    }
+
+    #[test]
+    fn fetch_update() {
+        let a = Atomic::<i32>::from_ptr(Ptr::null().with_tag(1));
+        epoch::pin(|scope| {
+            let prev = a.fetch_update(SeqCst, SeqCst, scope, |p| Some(p.with_tag(p.tag() + 2)));
+            assert_eq!(prev.unwrap().tag(), 1);
+            assert_eq!(a.load(SeqCst, scope).tag(), 3);
+
+            let prev = a.fetch_update(SeqCst, SeqCst, scope, |_| None);
+            assert_eq!(prev.unwrap_err().tag(), 3);
+            assert_eq!(a.load(SeqCst, scope).tag(), 3);
+        });
+    }
+
+    #[test]
+    fn fetch_update_stress() {
+        const THREADS: usize = 4;
+        const COUNT: usize = 1000;
+
+        let a = Arc::new(Atomic::new(0));
+        let threads = (0..THREADS).map(|_| {
+            let a = a.clone();
+            thread::spawn(move || {
+                for _ in 0..COUNT {
+                    epoch::pin(|scope| unsafe {
+                        // Allocate the new counter once and rewrite it on every attempt. It is
+                        // not shared until the update succeeds.
+                        let new = Owned::new(0).into_ptr(scope);
+                        let prev = a.fetch_update(SeqCst, SeqCst, scope, |p| {
+                            *(new.as_raw() as *mut usize) = *p.deref() + 1;
+                            Some(new)
+                        });
+                        scope.defer_free(prev.unwrap());
+                    });
+                }
+            })
+        }).collect::<Vec<_>>();
+
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        epoch::pin(|scope| unsafe {
+            assert_eq!(*a.load(SeqCst, scope).deref(), THREADS * COUNT);
+            scope.defer_free(a.swap(Ptr::null(), SeqCst, scope));
+        });
+    }
+
+    #[test]
+    fn load_ref() {
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        let a = Atomic::null();
+        epoch::pin(|scope| unsafe {
+            assert!(a.load_ref(SeqCst, scope).is_none());
+
+            a.store_owned(Owned::new(Point { x: 3, y: 4 }), SeqCst);
+            let p = a.load_ref(SeqCst, scope).unwrap();
+            assert_eq!((p.x, p.y), (3, 4));
+
+            scope.defer_free(a.swap(Ptr::null(), SeqCst, scope));
+        });
+    }
+
+    #[test]
+    fn new_uninit() {
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Elem(i32);
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, SeqCst);
+            }
+        }
+
+        // Dropping uninitialized memory doesn't run the destructor.
+        drop(Owned::<Elem>::new_uninit());
+        assert_eq!(DROPS.load(SeqCst), 0);
+
+        let mut o = Owned::<Elem>::new_uninit().with_tag(2);
+        o.write(Elem(7));
+        let o = unsafe { o.assume_init() };
+        assert_eq!(o.tag(), 2);
+
+        let a = Atomic::from_owned(o);
+        epoch::pin(|scope| {
+            let p = a.load(SeqCst, scope);
+            assert_eq!(p.tag(), 2);
+            assert_eq!(unsafe { p.deref().0 }, 7);
+
+            unsafe { scope.defer_drop(a.swap(Ptr::null(), SeqCst, scope)) }
+        });
+
+        // Threads from other tests may be pinned and hold back the epoch for a little while.
+        while DROPS.load(SeqCst) < 1 {
+            epoch::force_reclaim_all();
+            thread::yield_now();
+        }
+        assert_eq!(DROPS.load(SeqCst), 1);
+    }
+
+    #[test]
+    fn shared_outlives_unlink() {
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Elem(i32);
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, SeqCst);
+            }
+        }
+
+        let a = Arc::new(Atomic::new(Elem(7)));
+        let b = Arc::new(Atomic::null());
+
+        epoch::pin(|scope| {
+            // Two atomics and a local pointer all refer to the same object.
+            let p = a.load(SeqCst, scope);
+            b.store(p, SeqCst);
+            let q = b.load(SeqCst, scope);
+            assert_eq!(p.as_raw(), q.as_raw());
+
+            // Another thread unlinks the object and defers its destruction.
+            let (a, b) = (a.clone(), b.clone());
+            thread::spawn(move || {
+                epoch::pin(|scope| unsafe {
+                    let p = a.swap(Ptr::null(), SeqCst, scope);
+                    b.store(Ptr::null(), SeqCst);
+                    scope.defer_drop(p);
+                });
+                for _ in 0..100 {
+                    epoch::force_reclaim_all();
+                }
+            }).join().unwrap();
+
+            // This thread is still pinned, so the object must still be alive.
+            assert_eq!(DROPS.load(SeqCst), 0);
+            assert_eq!(unsafe { q.deref().0 }, 7);
+        });
+
+        // Now that nobody can reference it, the object gets reclaimed.
+        while DROPS.load(SeqCst) == 0 {
+            epoch::force_reclaim_all();
+            thread::yield_now();
+        }
+        assert_eq!(DROPS.load(SeqCst), 1);
+    }
+
+    #[test]
+    fn fetch_tag_bits() {
+        const THREADS: usize = 4;
+        const STEPS: usize = 10_000;
+
+        let a = Arc::new(Atomic::new(7u64));
+        let raw = epoch::pin(|scope| a.load(SeqCst, scope).as_raw() as usize);
+
+        // Every thread owns one tag bit and repeatedly sets and clears it.
+        let threads = (0..THREADS).map(|t| {
+            let a = a.clone();
+            thread::spawn(move || {
+                let bit = 1 << (t % 3);
+                for _ in 0..STEPS {
+                    epoch::pin(|scope| {
+                        let p = a.fetch_or(bit, SeqCst, scope);
+                        assert_eq!(p.as_raw() as usize, raw);
+                        let p = a.fetch_and(!bit, SeqCst, scope);
+                        assert_eq!(p.as_raw() as usize, raw);
+                    });
+                }
+            })
+        }).collect::<Vec<_>>();
+
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        epoch::pin(|scope| unsafe {
+            let p = a.load(SeqCst, scope);
+            assert_eq!(p.as_raw() as usize, raw);
+            assert_eq!(p.tag(), 0);
+            assert_eq!(*p.deref(), 7);
+
+            // Bits outside of the tag are ignored.
+            assert_eq!(a.fetch_or(!0, SeqCst, scope).as_raw() as usize, raw);
+            assert_eq!(a.load(SeqCst, scope).tag(), 7);
+            assert_eq!(a.fetch_and(0, SeqCst, scope).as_raw() as usize, raw);
+            assert_eq!(a.load(SeqCst, scope).tag(), 0);
+            assert_eq!(a.load(SeqCst, scope).as_raw() as usize, raw);
+
+            scope.defer_free(p);
+        });
+    }
+}
@@ -24,21 +24,31 @@
 //! populated with garbage, and when it becomes full, it is finally pushed into queue. This design
 //! reduces contention on data structures. The global queue cannot be explicitly accessed - the
 //! only way to interact with it is by calling function `defer_free`.
+//!
+//! # Destruction order
+//!
+//! Objects in a bag are destroyed in the order they were inserted, and bags are popped from the
+//! queue in the order they were pushed. Therefore, objects deferred by a thread are destroyed in
+//! the order of deferral. The only exception is when multiple threads are collecting garbage at
+//! the same time: then two different bags may be destroyed concurrently by different threads.
 
-use std::cell::UnsafeCell;
+use std::cell::{Cell, UnsafeCell};
 use std::cmp;
 use std::fmt;
 use std::mem;
 use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT};
-use std::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed, SeqCst};
+use std::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed, Release, SeqCst};
 
 use epoch::{self, Atomic, Owned, Scope, Ptr};
 
 /// Maximum number of objects a bag can contain.
 #[cfg(not(feature = "strict_gc"))]
-const MAX_OBJECTS: usize = 64;
+pub const MAX_OBJECTS: usize = 64;
 #[cfg(feature = "strict_gc")]
-const MAX_OBJECTS: usize = 4;
+pub const MAX_OBJECTS: usize = 4;
+
+/// Number of bags to destroy in a single collection step.
+const COLLECT_STEPS: usize = 8;
 
 /// The global epoch.
 ///
@@ -50,6 +60,57 @@ const MAX_OBJECTS: usize = 4;
 /// thread will hold a reference to it. That is the crux of safe memory reclamation.
 pub static EPOCH: AtomicUsize = ATOMIC_USIZE_INIT;
 
+thread_local! {
+    /// Number of objects destroyed by garbage collection on the current thread.
+    static DESTROYED: Cell<usize> = Cell::new(0);
+}
+
+/// Returns the number of objects destroyed by garbage collection on the current thread so far.
+///
+/// The number wraps around on overflow.
+pub fn destroyed() -> usize {
+    DESTROYED.with(|d| d.get())
+}
+
+/// A callback invoked whenever an object is reclaimed.
+type Callback = Box<Fn() + Send + Sync>;
+
+/// Pointer to the boxed callback registered by `on_reclaim`, or zero if there is none.
+static ON_RECLAIM: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// Registers a global `callback` that gets invoked whenever an object is reclaimed.
+///
+/// This is useful for feeding garbage collection events into metrics. The callback is invoked on
+/// whichever thread destroys the object, once for every object destroyed by any [`Garbage`],
+/// including the global one.
+///
+/// The callback runs in the middle of garbage collection, so it must be cheap and must not itself
+/// interact with the garbage collector (e.g. by deferring destruction of objects).
+///
+/// Registering a new callback replaces the previous one. The previous callback is never
+/// destroyed because another thread might be invoking it at the same time, so callbacks are best
+/// registered just once, when the program starts.
+///
+/// [`Garbage`]: struct.Garbage.html
+///
+/// # Examples
+///
+/// ```
+/// use coco::epoch;
+/// use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT};
+/// use std::sync::atomic::Ordering::Relaxed;
+///
+/// static RECLAIMED: AtomicUsize = ATOMIC_USIZE_INIT;
+///
+/// epoch::on_reclaim(Box::new(|| {
+///     RECLAIMED.fetch_add(1, Relaxed);
+/// }));
+/// ```
+pub fn on_reclaim(callback: Box<Fn() + Send + Sync>) {
+    let raw = Box::into_raw(Box::new(callback));
+    ON_RECLAIM.store(raw as usize, Release);
+}
+
 /// Holds removed objects that will be eventually destroyed.
 pub struct Bag {
     /// Number of objects in the bag.
@@ -78,6 +139,11 @@ impl Bag {
         self.len.load(Relaxed) == 0
     }
 
+    /// Returns the number of objects in the bag.
+    pub fn len(&self) -> usize {
+        self.len.load(Relaxed)
+    }
+
     /// Attempts to insert a garbage object into the bag and returns `true` if succeeded.
     pub fn try_insert<T>(&self, destroy: unsafe fn(*mut T, usize), object: *const T, count: usize)
                          -> bool {
@@ -105,14 +171,21 @@ impl Bag {
         }
     }
 
-    /// Destroys all objects in the bag.
+    /// Destroys all objects in the bag in insertion order and returns how many were destroyed.
     ///
     /// Note: can be called only once!
-    unsafe fn destroy_all_objects(&self) {
-        for cell in self.objects.iter().take(self.len.load(Relaxed)) {
+    unsafe fn destroy_all_objects(&self) -> usize {
+        let callback = ON_RECLAIM.load(Acquire) as *const Callback;
+        let len = self.len.load(Relaxed);
+        for cell in self.objects.iter().take(len) {
             let (destroy, object, count) = *cell.get();
             destroy(object, count);
+
+            if !callback.is_null() {
+                (*callback)();
+            }
         }
+        len
     }
 }
 
@@ -283,8 +356,27 @@ impl Garbage {
     ///
     /// This method collects several buffers worth of garbage objects.
     pub fn collect(&self, scope: &Scope) {
-        /// Number of bags to destroy.
-        const COLLECT_STEPS: usize = 8;
+        self.collect_bags(COLLECT_STEPS, scope);
+    }
+
+    /// Collects at most `steps` bags from the queue and returns the number of destroyed objects.
+    ///
+    /// If the current thread is already collecting, this returns zero right away.
+    fn collect_bags(&self, steps: usize, scope: &Scope) -> usize {
+        thread_local! {
+            /// Whether the current thread is in the middle of collecting garbage.
+            static COLLECTING: Cell<bool> = Cell::new(false);
+        }
+
+        // Popping a bag defers freeing of the old queue node, which may flush the thread-local bag
+        // and collect again. Only the outermost call collects, so the recursion doesn't get deeper
+        // with every expired bag. The outer loop keeps popping anyway.
+        if COLLECTING.with(|c| c.replace(true)) {
+            return 0;
+        }
+        defer! {
+            COLLECTING.with(|c| c.set(false))
+        }
 
         let epoch = EPOCH.load(SeqCst);
         let condition = |bag: &Bag| {
@@ -294,12 +386,15 @@ impl Garbage {
             cmp::min(diff, 0usize.wrapping_sub(diff)) > 2
         };
 
-        for _ in 0..COLLECT_STEPS {
+        let mut destroyed = 0;
+        for _ in 0..steps {
             match self.try_pop_if(&condition, scope) {
                 None => break,
-                Some(bag) => unsafe { bag.destroy_all_objects() },
+                Some(bag) => destroyed += unsafe { bag.destroy_all_objects() },
             }
         }
+        DESTROYED.with(|d| d.set(d.get().wrapping_add(destroyed)));
+        destroyed
     }
 
     /// Pushes a bag into the queue.
@@ -439,8 +534,25 @@ pub fn push(bag: Box<Bag>, scope: &Scope) {
 }
 
 /// Collects several bags from the global queue and destroys their objects.
-pub fn collect(scope: &Scope) {
-    global().collect(scope);
+///
+/// Returns the number of destroyed objects.
+pub fn collect(scope: &Scope) -> usize {
+    global().collect_bags(COLLECT_STEPS, scope)
+}
+
+/// Collects enough bags from the global queue to keep up with a thread that has pushed `bags`
+/// bags since it last collected.
+///
+/// Returns the number of destroyed objects.
+pub fn collect_proportional(bags: usize, scope: &Scope) -> usize {
+    global().collect_bags(cmp::max(COLLECT_STEPS, bags.saturating_mul(2)), scope)
+}
+
+/// Collects all expired bags from the global queue and destroys their objects.
+///
+/// Returns the number of destroyed objects.
+pub fn collect_all(scope: &Scope) -> usize {
+    global().collect_bags(usize::MAX, scope)
 }
 
 /// Destroys the global garbage.
@@ -459,11 +571,13 @@ pub unsafe fn destroy_global() {
 mod tests {
     extern crate rand;
 
+    use std::cell::Cell;
     use std::mem;
     use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT};
     use std::sync::atomic::Ordering::SeqCst;
     use std::sync::Arc;
     use std::thread;
+    use std::time::{Duration, Instant};
 
     use self::rand::{Rng, thread_rng};
 
@@ -741,4 +855,55 @@ mod tests {
         drop(g);
         assert_eq!(DROPS.load(SeqCst), COUNT * THREADS);
     }
+
+    #[test]
+    fn on_reclaim() {
+        const COUNT: usize = 10_000;
+        static OWN_CALLS: AtomicUsize = ATOMIC_USIZE_INIT;
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        thread_local! {
+            /// Number of times the callback was invoked on the current thread.
+            static CALLS: Cell<usize> = Cell::new(0);
+            /// Set when an `Elem` is destroyed, and cleared by the callback right after.
+            static DROPPED: Cell<bool> = Cell::new(false);
+        }
+
+        struct Elem(i32);
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, SeqCst);
+                DROPPED.with(|d| d.set(true));
+            }
+        }
+
+        // Objects deferred by concurrently running tests invoke the callback too, so only the
+        // invocations that follow the destruction of an `Elem` are attributed to this test.
+        super::on_reclaim(Box::new(|| {
+            CALLS.with(|c| c.set(c.get() + 1));
+            if DROPPED.with(|d| d.replace(false)) {
+                OWN_CALLS.fetch_add(1, SeqCst);
+            }
+        }));
+
+        epoch::pin(|scope| unsafe {
+            for i in 0..COUNT {
+                let a = epoch::Owned::new(Elem(i as i32)).into_ptr(scope);
+                scope.defer_drop(a);
+            }
+        });
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while OWN_CALLS.load(SeqCst) < COUNT {
+            assert!(Instant::now() < deadline, "the callback is not invoked for every object");
+
+            // Every object reclaimed by this thread invokes the callback exactly once.
+            let before = CALLS.with(|c| c.get());
+            let reclaimed = epoch::try_reclaim();
+            assert_eq!(CALLS.with(|c| c.get()) - before, reclaimed);
+        }
+        assert_eq!(OWN_CALLS.load(SeqCst), COUNT);
+        assert_eq!(DROPS.load(SeqCst), COUNT);
+    }
 }
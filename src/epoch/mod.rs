@@ -7,6 +7,11 @@
 //! [`Ptr`], which is an epoch-protected pointer through which the loaded object can be safely
 //! read.
 //!
+//! An [`Owned`] is a uniquely owned heap-allocated object that isn't yet shared with other
+//! threads. Once it is stored into an [`Atomic`] or converted into a [`Ptr`], it becomes shared and
+//! is no longer destroyed automatically: a [`Ptr`] never owns the object it points to, and
+//! destruction must be explicitly deferred once the object is no longer reachable.
+//!
 //! # Pinning
 //!
 //! Before an [`Atomic`] can be loaded, the current thread must be pinned. By pinning a thread we
@@ -25,17 +30,21 @@
 //!
 //! [`Atomic`]: struct.Atomic.html
 //! [`Garbage`]: struct.Garbage.html
+//! [`Owned`]: struct.Owned.html
 //! [`Ptr`]: struct.Ptr.html
 //! [`defer_free`]: struct.Scope.html#method.defer_free
 //! [`defer_drop`]: struct.Scope.html#method.defer_drop
 
 mod atomic;
 mod garbage;
+mod pool;
 mod thread;
 
 pub use self::atomic::{Atomic, Owned, Ptr};
-pub use self::garbage::Garbage;
-pub use self::thread::{Scope, is_pinned, pin, unprotected};
+pub use self::garbage::{Garbage, on_reclaim};
+pub use self::pool::NodePool;
+pub use self::thread::{DeferResult, Scope, defer_free_unprotected, force_reclaim_all, is_pinned,
+                       pin, pre_register, set_bag_capacity, try_pin, try_reclaim, unprotected};
 
 #[cfg(feature = "internals")]
 pub use self::garbage::destroy_global;
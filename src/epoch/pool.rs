@@ -0,0 +1,165 @@
+//! Pools of reusable allocations.
+//!
+//! Allocating and freeing a node for every operation can be expensive. Instead of freeing a
+//! removed node, a data structure can hand it to [`Scope::defer_recycle`], which returns the
+//! allocation to a [`NodePool`] once no thread can be holding a reference to it anymore. New
+//! nodes are then allocated from the pool, reusing memory that is already there.
+//!
+//! [`NodePool`]: struct.NodePool.html
+//! [`Scope::defer_recycle`]: struct.Scope.html#method.defer_recycle
+
+use std::fmt;
+use std::ptr;
+use std::sync::{Arc, Mutex};
+
+use epoch::Owned;
+
+/// The shared state of a pool.
+struct Inner<T> {
+    /// Allocations that are ready to be reused. Their contents are uninitialized.
+    slots: Mutex<Vec<*mut T>>,
+}
+
+impl<T> Drop for Inner<T> {
+    fn drop(&mut self) {
+        let slots = self.slots.get_mut().unwrap_or_else(|e| e.into_inner());
+        for &raw in slots.iter() {
+            // The slots hold no values, so just free the memory.
+            unsafe { drop(Vec::from_raw_parts(raw, 0, 1)) }
+        }
+    }
+}
+
+/// A pool of allocations that can be reused for new objects of type `T`.
+///
+/// Cloning a pool creates a new handle to the same pool. Allocations owned by the pool are freed
+/// once the last handle is dropped and all objects deferred with [`Scope::defer_recycle`] into it
+/// have been recycled.
+///
+/// [`Scope::defer_recycle`]: struct.Scope.html#method.defer_recycle
+///
+/// # Examples
+///
+/// ```
+/// use coco::epoch::{self, NodePool};
+///
+/// let pool = NodePool::new();
+///
+/// epoch::pin(|scope| unsafe {
+///     let p = pool.alloc(7).into_ptr(scope);
+///     scope.defer_recycle(p, &pool);
+/// });
+///
+/// // Once the object is reclaimed, its allocation returns to the pool.
+/// while pool.is_empty() {
+///     epoch::force_reclaim_all();
+/// }
+/// assert_eq!(*pool.alloc(8), 8);
+/// ```
+pub struct NodePool<T> {
+    inner: Arc<Inner<T>>,
+}
+
+unsafe impl<T: Send> Send for NodePool<T> {}
+unsafe impl<T: Send> Sync for NodePool<T> {}
+
+impl<T> NodePool<T> {
+    /// Returns a new, empty pool.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::epoch::NodePool;
+    ///
+    /// let pool = NodePool::<i32>::new();
+    /// assert!(pool.is_empty());
+    /// ```
+    pub fn new() -> Self {
+        NodePool {
+            inner: Arc::new(Inner { slots: Mutex::new(Vec::new()) }),
+        }
+    }
+
+    /// Returns the number of allocations that are ready to be reused.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::epoch::NodePool;
+    ///
+    /// let pool = NodePool::<i32>::new();
+    /// assert_eq!(pool.len(), 0);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.inner.slots.lock().unwrap().len()
+    }
+
+    /// Returns `true` if there are no allocations ready to be reused.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::epoch::NodePool;
+    ///
+    /// let pool = NodePool::<i32>::new();
+    /// assert!(pool.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Moves `value` into an allocation from the pool, or into a new one if the pool is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::epoch::NodePool;
+    ///
+    /// let pool = NodePool::new();
+    /// let o = pool.alloc(1234);
+    /// assert_eq!(*o, 1234);
+    /// ```
+    pub fn alloc(&self, value: T) -> Owned<T> {
+        let slot = self.inner.slots.lock().unwrap().pop();
+        match slot {
+            None => Owned::new(value),
+            Some(raw) => unsafe {
+                ptr::write(raw, value);
+                Owned::from_raw(raw)
+            },
+        }
+    }
+}
+
+impl<T> Clone for NodePool<T> {
+    fn clone(&self) -> Self {
+        NodePool { inner: self.inner.clone() }
+    }
+}
+
+impl<T> Default for NodePool<T> {
+    fn default() -> Self {
+        NodePool::new()
+    }
+}
+
+impl<T> fmt::Debug for NodePool<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NodePool {{ ... }}")
+    }
+}
+
+/// Returns a handle to `pool` encoded as an integer.
+///
+/// The handle keeps the pool alive until it is consumed by `recycle`.
+pub fn into_handle<T>(pool: &NodePool<T>) -> usize {
+    Arc::into_raw(pool.inner.clone()) as usize
+}
+
+/// Returns the allocation at `ptr` back to the pool behind `handle` and consumes the handle.
+///
+/// The value inside the allocation is not dropped.
+pub unsafe fn recycle<T>(ptr: *mut T, handle: usize) {
+    let inner = Arc::from_raw(handle as *const Inner<T>);
+    inner.slots.lock().unwrap().push(ptr);
+}
@@ -9,6 +9,10 @@
 //! Registered threads are tracked in a global lock-free singly-linked list of thread entries. The
 //! head of this list is accessed by calling the `participants` function.
 //!
+//! Entries are never removed from the list. When a thread exits, it's entry becomes vacant and is
+//! later reused by a newly registered thread. This way programs that spawn lots of short-lived
+//! threads don't allocate a new entry for each one of them.
+//!
 //! # Thread entries
 //!
 //! Thread entries are implemented as the `Thread` data type. Every entry contains an integer that
@@ -17,11 +21,12 @@
 
 use std::cell::Cell;
 use std::mem;
-use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT};
+use std::sync::atomic::{AtomicBool, AtomicUsize, ATOMIC_USIZE_INIT};
 use std::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed, Release, SeqCst};
 
-use epoch::{Atomic, Owned, Ptr};
+use epoch::{Atomic, NodePool, Owned, Ptr};
 use epoch::garbage::{self, Bag, EPOCH};
+use epoch::pool;
 
 thread_local! {
     /// The thread registration harness.
@@ -35,6 +40,17 @@ thread_local! {
         pin_count: Cell::new(0),
         bag: Cell::new(Box::into_raw(Box::new(Bag::new()))),
     };
+
+    /// Maximum number of objects in the local bag before it gets flushed.
+    static BAG_CAPACITY: Cell<usize> = Cell::new(garbage::MAX_OBJECTS);
+
+    /// Number of bags this thread has flushed since it last helped collect garbage when pinning.
+    static FLUSHED: Cell<usize> = Cell::new(0);
+
+    /// Whether the harness is initialized and the thread is registered.
+    ///
+    /// Unlike `HARNESS`, this can be checked without triggering registration.
+    static IS_REGISTERED: Cell<bool> = Cell::new(false);
 }
 
 /// Holds thread-local data and unregisters the thread when dropped.
@@ -51,6 +67,8 @@ struct Harness {
 
 impl Drop for Harness {
     fn drop(&mut self) {
+        IS_REGISTERED.with(|r| r.set(false));
+
         // Now that the thread is exiting, we must move the local bag into the global garbage
         // queue. Also, let's try advancing the epoch and help free some garbage.
         let thread = unsafe { &*self.thread };
@@ -65,6 +83,14 @@ impl Drop for Harness {
         try_advance(pin);
         garbage::collect(pin);
 
+        // If this is the last registered thread, nobody else is pinned and holding on to garbage,
+        // so the epoch can be advanced far enough for all of it to be reclaimed.
+        if REGISTERED.fetch_sub(1, SeqCst) == 1 {
+            pin.push_bag();
+            advance_fully(thread, pin);
+            garbage::collect_all(pin);
+        }
+
         // Push the local bag into the global garbage queue.
         let bag = unsafe { Box::from_raw(self.bag.get()) };
         garbage::push(bag, pin);
@@ -72,7 +98,7 @@ impl Drop for Harness {
         // Manually unpin the thread.
         thread.set_unpinned();
 
-        // Mark the thread entry as deleted.
+        // Make the thread entry vacant.
         thread.unregister();
     }
 }
@@ -82,8 +108,9 @@ struct Thread {
     /// The least significant bit is set if the thread is currently pinned. The rest of the bits
     /// encode the current epoch.
     state: AtomicUsize,
-    /// The next thread in the linked list of participants. If the tag is 1, this entry is deleted
-    /// and can be unlinked from the list.
+    /// Whether the entry belongs to a registered thread. If not, the entry is vacant.
+    in_use: AtomicBool,
+    /// The next thread in the linked list of participants.
     next: Atomic<Thread>,
 }
 
@@ -122,22 +149,41 @@ impl Thread {
         self.state.store(0, Release);
     }
 
-    /// Registers a thread by adding a new entry to the list of participanting threads.
+    /// Registers a thread by claiming a vacant entry or adding a new entry to the list of
+    /// participanting threads.
     ///
-    /// Returns a pointer to the newly allocated entry.
+    /// Returns a pointer to the claimed entry.
     fn register() -> *const Thread {
+        REGISTERED.fetch_add(1, SeqCst);
+        IS_REGISTERED.with(|r| r.set(true));
         let list = participants();
 
-        let mut new = Owned::new(Thread {
-            state: AtomicUsize::new(0),
-            next: Atomic::null(),
-        });
-
         // This code is executing while the thread harness is initializing, so normal pinning would
         // try to access it while it is being initialized. Such accesses fail with a panic. We must
         // therefore cheat by creating a fake pin.
         let pin = unsafe { &mem::zeroed::<Scope>() };
 
+        // First try claiming an entry left behind by an exited thread. The entry is already reset
+        // because the exited thread had unpinned before making it vacant.
+        let mut curr = list.load(Acquire, pin);
+        while let Some(c) = unsafe { curr.as_ref() } {
+            if !c.in_use.load(Relaxed) &&
+                c.in_use.compare_exchange(false, true, Acquire, Relaxed).is_ok()
+            {
+                return c;
+            }
+            curr = c.next.load(Acquire, pin);
+        }
+
+        #[cfg(test)]
+        ALLOCATED.fetch_add(1, Relaxed);
+
+        let mut new = Owned::new(Thread {
+            state: AtomicUsize::new(0),
+            in_use: AtomicBool::new(true),
+            next: Atomic::null(),
+        });
+
         let mut head = list.load(Acquire, pin);
         loop {
             new.next.store(head, Relaxed);
@@ -153,27 +199,21 @@ impl Thread {
         }
     }
 
-    /// Unregisters the thread by marking it's entry as deleted.
+    /// Unregisters the thread by making it's entry vacant.
     ///
-    /// This function doesn't physically remove the entry from the linked list, though. That will
-    /// do a future call to `try_advance`.
+    /// Must be called only after the thread gets unpinned for the last time.
     fn unregister(&self) {
-        // This code is executing while the thread harness is initializing, so normal pinning would
-        // try to access it while it is being initialized. Such accesses fail with a panic. We must
-        // therefore cheat by creating a fake pin.
-        let pin = unsafe { &mem::zeroed::<Scope>() };
-
-        // Simply mark the next-pointer in this thread's entry.
-        let mut next = self.next.load(Acquire, pin);
-        while next.tag() == 0 {
-            match self.next.compare_and_swap(next, next.with_tag(1), AcqRel, pin) {
-                Ok(()) => break,
-                Err(n) => next = n,
-            }
-        }
+        self.in_use.store(false, Release);
     }
 }
 
+/// Number of thread entries allocated so far.
+#[cfg(test)]
+static ALLOCATED: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// Number of currently registered threads.
+static REGISTERED: AtomicUsize = ATOMIC_USIZE_INIT;
+
 /// Returns a reference to the head pointer of the list of participating threads.
 fn participants() -> &'static Atomic<Thread> {
     static PARTICIPANTS: AtomicUsize = ATOMIC_USIZE_INIT;
@@ -189,43 +229,21 @@ pub fn try_advance(scope: &Scope) {
     let epoch = EPOCH.load(SeqCst);
 
     // Traverse the linked list of participating threads.
-    let mut pred = participants();
-    let mut curr = pred.load(Acquire, scope);
+    let mut curr = participants().load(Acquire, scope);
 
     while let Some(c) = unsafe { curr.as_ref() } {
-        let succ = c.next.load(Acquire, scope);
-
-        if succ.tag() == 1 {
-            // This thread has exited. Try unlinking it from the list.
-            let succ = succ.with_tag(0);
-
-            if pred.compare_and_swap(curr, succ, AcqRel, scope).is_err() {
-                // We lost the race to unlink the thread. Usually that means we should traverse the
-                // list again from the beginning, but since another thread trying to advance the
-                // epoch has won the race, we leave the job to that one.
-                return;
-            }
-
-            // The unlinked entry can later be freed.
-            unsafe { scope.defer_free(curr) }
-
-            // Move forward, but don't change the predecessor.
-            curr = succ;
-        } else {
-            let thread_state = c.state.load(SeqCst);
-            let thread_is_pinned = thread_state & 1 == 1;
-            let thread_epoch = thread_state & !1;
-
-            // If the thread was pinned in a different epoch, we cannot advance the global epoch
-            // just yet.
-            if thread_is_pinned && thread_epoch != epoch {
-                return;
-            }
-
-            // Move one step forward.
-            pred = &c.next;
-            curr = succ;
+        let thread_state = c.state.load(SeqCst);
+        let thread_is_pinned = thread_state & 1 == 1;
+        let thread_epoch = thread_state & !1;
+
+        // If the thread was pinned in a different epoch, we cannot advance the global epoch
+        // just yet.
+        if thread_is_pinned && thread_epoch != epoch {
+            return;
         }
+
+        // Move one step forward.
+        curr = c.next.load(Acquire, scope);
     }
 
     // All pinned threads were pinned in the current global epoch.
@@ -233,6 +251,43 @@ pub fn try_advance(scope: &Scope) {
     EPOCH.compare_and_swap(epoch, epoch.wrapping_add(2), SeqCst);
 }
 
+/// Advances the global epoch enough times for all garbage currently in the queue to expire.
+///
+/// Every advancement requires the current thread to be pinned in the current epoch, so the thread
+/// is repinned each time. This means `scope` must not hold any pointers loaded before the call.
+/// Advancement stops as soon as another thread pinned in an older epoch gets in the way.
+fn advance_fully(thread: &Thread, scope: &Scope) {
+    /// A bag expires once the global epoch has advanced twice since it was pushed.
+    const ADVANCE_STEPS: usize = 2;
+
+    for _ in 0..ADVANCE_STEPS {
+        thread.set_unpinned();
+        thread.set_pinned(scope);
+        try_advance(scope);
+    }
+}
+
+/// Frees an array of `count` elements at `ptr`, but doesn't run the destructors.
+unsafe fn free<T>(ptr: *mut T, count: usize) {
+    drop(Vec::from_raw_parts(ptr, 0, count));
+}
+
+/// Runs the destructors of an array of `count` elements at `ptr` and frees the memory.
+unsafe fn destruct<T>(ptr: *mut T, count: usize) {
+    drop(Vec::from_raw_parts(ptr, count, count));
+}
+
+/// The outcome of deferring destruction of an object.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum DeferResult {
+    /// The object was inserted into the thread-local buffer.
+    Deferred,
+
+    /// The thread-local buffer was full, so it was flushed and some garbage was collected before
+    /// inserting the object.
+    Flushed,
+}
+
 /// A witness that the current thread is pinned.
 ///
 /// A reference to `Scope` is proof that the current thread is pinned. Lots of methods that interact
@@ -265,28 +320,42 @@ impl Scope {
     /// [`Garbage`]: struct.Garbage.html
     /// [`flush`]: struct.Scope.html#method.flush
     pub unsafe fn defer_free<T>(&self, ptr: Ptr<T>) {
-        let object = ptr.as_raw();
-        let count = 1;
-
-        unsafe fn free<T>(ptr: *mut T, count: usize) {
-            // Free the memory, but don't run the destructors.
-            drop(Vec::from_raw_parts(ptr as *mut T, 0, count));
-        }
-
-        loop {
-            // Get the thread-local bag.
-            let cell = &*self.bag;
-            let bag = cell.get();
-
-            // Try inserting the object into the bag.
-            if (*bag).try_insert(free::<T>, object, count) {
-                // Success! We're done.
-                break;
-            }
+        self.defer_free_array(ptr, 1)
+    }
 
-            // Flush the garbage and create a new bag.
-            self.flush();
-        }
+    /// Stashes away an object that will later be freed, and reports what happened to the
+    /// thread-local buffer.
+    ///
+    /// This is just like [`defer_free`], except it returns [`DeferResult::Flushed`] if the buffer
+    /// was full, so it had to be flushed and some garbage collection was performed before the
+    /// object got inserted. Otherwise, [`DeferResult::Deferred`] is returned.
+    ///
+    /// [`defer_free`]: struct.Scope.html#method.defer_free
+    /// [`DeferResult::Flushed`]: enum.DeferResult.html#variant.Flushed
+    /// [`DeferResult::Deferred`]: enum.DeferResult.html#variant.Deferred
+    ///
+    /// # Safety
+    ///
+    /// The same conditions apply as for [`defer_free`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::epoch::{self, DeferResult, Owned};
+    ///
+    /// epoch::pin(|scope| unsafe {
+    ///     let mut flushes = 0;
+    ///     for i in 0..1000 {
+    ///         let p = Owned::new(i).into_ptr(scope);
+    ///         if scope.defer_free_checked(p) == DeferResult::Flushed {
+    ///             flushes += 1;
+    ///         }
+    ///     }
+    ///     assert!(flushes > 0);
+    /// });
+    /// ```
+    pub unsafe fn defer_free_checked<T>(&self, ptr: Ptr<T>) -> DeferResult {
+        self.defer_destroy(free::<T>, ptr.as_raw(), 1)
     }
 
     /// Adds an object that will later be dropped and freed.
@@ -297,29 +366,139 @@ impl Scope {
     /// This method inserts the object into the garbage buffer. When the buffers becomes full, it's
     /// objects are flushed into the garbage queue.
     ///
+    /// Objects deferred by the same thread are dropped in the order they were deferred, unless
+    /// other threads are collecting garbage at the same time, in which case objects from different
+    /// buffers may be dropped concurrently.
+    ///
     /// Note: The object must be `Send + 'self`.
     pub unsafe fn defer_drop<T>(&self, ptr: Ptr<T>) {
-        let object = ptr.as_raw();
-        let count = 1;
+        self.defer_drop_array(ptr, 1)
+    }
 
-        unsafe fn destruct<T>(ptr: *mut T, count: usize) {
-            // Run the destructors and free the memory.
-            drop(Vec::from_raw_parts(ptr, count, count));
-        }
+    /// Stashes away an array that will later be freed.
+    ///
+    /// The array starts at `ptr` and consists of `len` elements of type `T`. The destructors of the
+    /// elements will not be run. This is the array counterpart of [`defer_free`].
+    ///
+    /// [`defer_free`]: struct.Scope.html#method.defer_free
+    ///
+    /// # Safety
+    ///
+    /// The array must have been allocated as a boxed slice (or, equivalently, a `Vec` whose
+    /// capacity equals `len`), and it must be unreachable by threads pinned after this call.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::epoch::{self, Atomic, Ptr};
+    /// use std::sync::atomic::Ordering::SeqCst;
+    ///
+    /// let slots = (0..10).map(Atomic::new).collect::<Vec<_>>().into_boxed_slice();
+    /// let len = slots.len();
+    /// let raw = Box::into_raw(slots) as *const Atomic<i32>;
+    ///
+    /// epoch::pin(|scope| unsafe {
+    ///     let slot = &*raw.offset(3);
+    ///     assert_eq!(*slot.load(SeqCst, scope).deref(), 3);
+    ///
+    ///     // Free the values in the slots, and then the array itself.
+    ///     for i in 0..len {
+    ///         scope.defer_drop((*raw.offset(i as isize)).load(SeqCst, scope));
+    ///     }
+    ///     scope.defer_free_array(Ptr::from_raw(raw), len);
+    /// });
+    /// ```
+    pub unsafe fn defer_free_array<T>(&self, ptr: Ptr<T>, len: usize) {
+        self.defer_destroy(free::<T>, ptr.as_raw(), len);
+    }
 
+    /// Adds an array that will later be dropped and freed.
+    ///
+    /// The array starts at `ptr` and consists of `len` elements of type `T`. The destructors of the
+    /// elements will be run before freeing the memory. This is the array counterpart of
+    /// [`defer_drop`].
+    ///
+    /// [`defer_drop`]: struct.Scope.html#method.defer_drop
+    ///
+    /// # Safety
+    ///
+    /// The array must have been allocated as a boxed slice (or, equivalently, a `Vec` whose
+    /// capacity equals `len`), and it must be unreachable by threads pinned after this call. The
+    /// elements must be `Send + 'self`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::epoch::{self, Ptr};
+    ///
+    /// let array = vec![String::from("a"), String::from("b")].into_boxed_slice();
+    /// let len = array.len();
+    /// let raw = Box::into_raw(array) as *const String;
+    ///
+    /// epoch::pin(|scope| unsafe {
+    ///     scope.defer_drop_array(Ptr::from_raw(raw), len);
+    /// });
+    /// ```
+    pub unsafe fn defer_drop_array<T>(&self, ptr: Ptr<T>, len: usize) {
+        self.defer_destroy(destruct::<T>, ptr.as_raw(), len);
+    }
+
+    /// Stashes away an object whose allocation will later be returned to `pool` for reuse.
+    ///
+    /// This is like [`defer_free`], except the memory isn't freed: once no pinned thread can be
+    /// holding a reference to the object anymore, the allocation is handed to `pool`, where
+    /// [`NodePool::alloc`] can pick it up. The object's destructor is not run.
+    ///
+    /// The deferred object keeps the pool alive until it is recycled.
+    ///
+    /// [`defer_free`]: struct.Scope.html#method.defer_free
+    /// [`NodePool::alloc`]: struct.NodePool.html#method.alloc
+    ///
+    /// # Safety
+    ///
+    /// The same conditions apply as for [`defer_free`]. In addition, `ptr` must have been
+    /// allocated by `Owned::new` or by a `NodePool<T>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::epoch::{self, NodePool};
+    ///
+    /// let pool = NodePool::new();
+    /// epoch::pin(|scope| unsafe {
+    ///     let p = pool.alloc(1).into_ptr(scope);
+    ///     scope.defer_recycle(p, &pool);
+    /// });
+    /// ```
+    pub unsafe fn defer_recycle<T>(&self, ptr: Ptr<T>, pool: &NodePool<T>) {
+        // The bag entry's count slot carries the pool handle instead.
+        self.defer_destroy(pool::recycle::<T>, ptr.as_raw(), pool::into_handle(pool));
+    }
+
+    /// Inserts an object with the specified destructor into the thread-local bag.
+    unsafe fn defer_destroy<T>(
+        &self,
+        destroy: unsafe fn(*mut T, usize),
+        object: *const T,
+        count: usize,
+    ) -> DeferResult {
+        let capacity = BAG_CAPACITY.with(|c| c.get());
+
+        let mut result = DeferResult::Deferred;
         loop {
             // Get the thread-local bag.
             let cell = &*self.bag;
             let bag = cell.get();
 
-            // Try inserting the object into the bag.
-            if (*bag).try_insert(destruct::<T>, object, count) {
+            // Try inserting the object into the bag, unless it's already at capacity.
+            if (*bag).len() < capacity && (*bag).try_insert(destroy, object, count) {
                 // Success! We're done.
-                break;
+                return result;
             }
 
             // Flush the garbage and create a new bag.
             self.flush();
+            result = DeferResult::Flushed;
         }
     }
 
@@ -330,24 +509,116 @@ impl Scope {
     ///
     /// [`defer_free`]: struct.Scope.html#method.defer_free
     pub fn flush(&self) {
+        if self.push_bag() {
+            // Spare some cycles on garbage collection.
+            // Note: This may itself produce garbage and allocate new bags.
+            try_advance(self);
+            garbage::collect(self);
+        }
+    }
+
+    /// Briefly unpins and then pins the current thread again.
+    ///
+    /// Staying pinned for a long time, e.g. during a long traversal of a data structure, prevents
+    /// the global epoch from advancing and therefore blocks reclamation of garbage produced by all
+    /// threads. Calling `repin` every now and then lets the epoch advance in the meantime.
+    ///
+    /// If this is an unprotected scope, the method does nothing.
+    ///
+    /// # Safety
+    ///
+    /// Repinning invalidates every [`Ptr`] loaded by the current thread before the call, including
+    /// the ones obtained in enclosing calls to [`pin`]. The objects they point to might get
+    /// destroyed right after the thread gets unpinned, so such pointers must not be dereferenced
+    /// once `repin` returns. Traversals must restart from a location loaded afresh.
+    ///
+    /// [`Ptr`]: struct.Ptr.html
+    /// [`pin`]: fn.pin.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::epoch::{self, Atomic};
+    /// use std::sync::atomic::Ordering::SeqCst;
+    ///
+    /// let a = Atomic::new(7);
+    ///
+    /// epoch::pin(|scope| {
+    ///     for _ in 0..1000 {
+    ///         // Pointers loaded in previous iterations are invalidated here.
+    ///         unsafe { scope.repin() }
+    ///
+    ///         let p = a.load(SeqCst, scope);
+    ///         assert_eq!(unsafe { *p.deref() }, 7);
+    ///     }
+    /// });
+    /// ```
+    pub unsafe fn repin(&self) {
+        if self.bag.is_null() {
+            return;
+        }
+
+        HARNESS.with(|harness| {
+            let thread = &*harness.thread;
+            thread.set_unpinned();
+            thread.set_pinned(self);
+        })
+    }
+
+    /// Returns the global epoch at the moment the current thread was pinned.
+    ///
+    /// This is useful for correlating operations with epoch boundaries, e.g. in custom
+    /// reclamation schemes layered on top of the garbage collector. The epoch number increases by
+    /// one with every advancement, and wraps around on overflow. Calling [`repin`] updates it.
+    ///
+    /// For an unprotected scope, the current global epoch is returned instead.
+    ///
+    /// [`repin`]: struct.Scope.html#method.repin
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::epoch;
+    ///
+    /// let e = epoch::pin(|scope| scope.epoch());
+    /// epoch::force_reclaim_all();
+    /// let now = epoch::pin(|scope| scope.epoch());
+    ///
+    /// // The epoch never goes backwards.
+    /// assert!(now.wrapping_sub(e) < usize::MAX / 2);
+    /// ```
+    pub fn epoch(&self) -> usize {
+        let state = if self.bag.is_null() {
+            EPOCH.load(Relaxed)
+        } else {
+            HARNESS.with(|harness| unsafe { (*harness.thread).state.load(Relaxed) })
+        };
+
+        // The least significant bit tells whether the thread is pinned.
+        state >> 1
+    }
+
+    /// Pushes the thread-local bag into the global garbage queue, unless it is empty.
+    ///
+    /// Returns `true` if the bag was pushed.
+    fn push_bag(&self) -> bool {
         unsafe {
             // Get the thread-local bag.
             let cell = &*self.bag;
             let bag = cell.get();
 
-            if !(*bag).is_empty() {
-                // The bag is full. We must replace it with a fresh one.
-                cell.set(Box::into_raw(Box::new(Bag::new())));
+            if (*bag).is_empty() {
+                return false;
+            }
 
-                // Push the old bag into the garbage queue.
-                let bag = Box::from_raw(bag);
-                garbage::push(bag, self);
+            // The bag is not empty. We must replace it with a fresh one.
+            cell.set(Box::into_raw(Box::new(Bag::new())));
 
-                // Spare some cycles on garbage collection.
-                // Note: This may itself produce garbage and allocate new bags.
-                try_advance(self);
-                garbage::collect(self);
-            }
+            // Push the old bag into the garbage queue.
+            let bag = Box::from_raw(bag);
+            garbage::push(bag, self);
+            FLUSHED.with(|f| f.set(f.get().wrapping_add(1)));
+            true
         }
     }
 }
@@ -392,9 +663,17 @@ where
             harness.pin_count.set(count.wrapping_add(1));
 
             // If the counter progressed enough, try advancing the epoch and collecting garbage.
-            if count % PINS_BETWEEN_COLLECT == 0 {
+            //
+            // A thread that has flushed garbage since then also helps right away, collecting in
+            // proportion to how much it produced. Collecting while flushing isn't enough on its
+            // own, because the thread is pinned in an old epoch at that point, which stops the
+            // epoch from advancing far enough for its own garbage to expire. Now it has just been
+            // pinned in the current epoch, so it doesn't get in the way.
+            let flushed = FLUSHED.with(|f| f.get());
+            if count % PINS_BETWEEN_COLLECT == 0 || flushed > 0 {
+                FLUSHED.with(|f| f.set(0));
                 try_advance(pin);
-                garbage::collect(pin);
+                garbage::collect_proportional(flushed, pin);
             }
         }
 
@@ -411,6 +690,186 @@ where
     })
 }
 
+/// Sets how many deferred objects the current thread buffers before flushing them.
+///
+/// Objects passed to the `defer_*` methods of [`Scope`] are collected in a thread-local buffer,
+/// which is flushed into the global garbage queue once it holds `n` objects. Flushing is also
+/// when some global garbage gets collected, so a lower threshold reclaims memory sooner, at the
+/// cost of flushing more often.
+///
+/// The buffer has a fixed size, so this can only lower the flush threshold, never raise it. The
+/// capacity is clamped between 1 and the default: 64 objects, or only 4 with the `strict_gc`
+/// feature.
+///
+/// [`Scope`]: struct.Scope.html
+///
+/// # Examples
+///
+/// ```
+/// use coco::epoch::{self, DeferResult, Owned};
+///
+/// epoch::set_bag_capacity(2);
+/// epoch::pin(|scope| unsafe {
+///     scope.flush();
+///     assert_eq!(scope.defer_free_checked(Owned::new(1).into_ptr(scope)), DeferResult::Deferred);
+///     assert_eq!(scope.defer_free_checked(Owned::new(2).into_ptr(scope)), DeferResult::Deferred);
+///     assert_eq!(scope.defer_free_checked(Owned::new(3).into_ptr(scope)), DeferResult::Flushed);
+/// });
+/// ```
+pub fn set_bag_capacity(n: usize) {
+    BAG_CAPACITY.with(|c| c.set(n.clamp(1, garbage::MAX_OBJECTS)));
+}
+
+/// Pins the current thread only if it is already registered.
+///
+/// The first call to [`pin`] on a thread registers it with the garbage collector, which allocates
+/// memory. Threads that must not allocate on their hot path can register ahead of time by calling
+/// [`pre_register`], and then use `try_pin`, which returns `None` instead of registering.
+///
+/// Note that even a registered thread may occasionally allocate while pinned, e.g. when garbage
+/// collection needs a fresh buffer for deferred objects.
+///
+/// [`pin`]: fn.pin.html
+/// [`pre_register`]: fn.pre_register.html
+///
+/// # Examples
+///
+/// ```
+/// use coco::epoch;
+/// use std::thread;
+///
+/// thread::spawn(|| {
+///     assert_eq!(epoch::try_pin(|_| 1), None);
+///
+///     epoch::pre_register();
+///     assert_eq!(epoch::try_pin(|_| 1), Some(1));
+/// }).join().unwrap();
+/// ```
+pub fn try_pin<F, T>(f: F) -> Option<T>
+where
+    F: FnOnce(&Scope) -> T
+{
+    if IS_REGISTERED.with(|r| r.get()) {
+        Some(pin(f))
+    } else {
+        None
+    }
+}
+
+/// Registers the current thread with the garbage collector, unless it is already registered.
+///
+/// Registration allocates memory, so threads that want to avoid allocating later on should call
+/// this when they start. See [`try_pin`] for more.
+///
+/// [`try_pin`]: fn.try_pin.html
+///
+/// # Examples
+///
+/// ```
+/// use coco::epoch;
+///
+/// epoch::pre_register();
+/// assert!(epoch::try_pin(|_| ()).is_some());
+/// ```
+pub fn pre_register() {
+    HARNESS.with(|_| ());
+}
+
+/// Attempts to reclaim some garbage right now.
+///
+/// Garbage is normally reclaimed automatically from time to time, whenever threads get pinned or
+/// produce garbage. This function can be used to pay the cost of reclamation at a convenient
+/// moment instead, e.g. when the program is idle.
+///
+/// The thread-local garbage is flushed, then an attempt is made to advance the global epoch, and
+/// finally several bags worth of garbage are destroyed. Garbage becomes safe for destruction only
+/// after the global epoch advances a few times, so it usually takes several calls before recently
+/// deferred objects get reclaimed.
+///
+/// Returns the number of reclaimed objects.
+///
+/// # Examples
+///
+/// ```
+/// use coco::epoch::{self, Owned};
+///
+/// epoch::pin(|scope| unsafe {
+///     for i in 0..100 {
+///         scope.defer_free(Owned::new(i).into_ptr(scope));
+///     }
+/// });
+///
+/// let mut reclaimed = 0;
+/// while reclaimed < 100 {
+///     reclaimed += epoch::try_reclaim();
+/// }
+/// ```
+pub fn try_reclaim() -> usize {
+    // Pinning may help collect some garbage before the closure runs, so count everything this
+    // thread destroys in the meantime.
+    let before = garbage::destroyed();
+
+    pin(|scope| {
+        scope.push_bag();
+        try_advance(scope);
+        garbage::collect(scope);
+    });
+
+    garbage::destroyed().wrapping_sub(before)
+}
+
+/// Reclaims as much garbage as possible right now.
+///
+/// Unlike [`try_reclaim`], this function flushes the thread-local garbage, advances the global
+/// epoch as far as it can, and then destroys every expired bag in the queue rather than just a
+/// few of them. If no other thread is pinned, all outstanding garbage gets reclaimed. Threads
+/// that are pinned in an older epoch still block reclamation of the garbage they might observe.
+///
+/// If the current thread is already pinned, the epoch cannot be advanced past it, so only
+/// garbage that has already expired gets destroyed.
+///
+/// The same reclamation happens automatically when the last registered thread exits.
+///
+/// Returns the number of reclaimed objects.
+///
+/// [`try_reclaim`]: fn.try_reclaim.html
+///
+/// # Examples
+///
+/// ```
+/// use coco::epoch::{self, Owned};
+///
+/// epoch::pin(|scope| unsafe {
+///     for i in 0..100 {
+///         scope.defer_free(Owned::new(i).into_ptr(scope));
+///     }
+/// });
+///
+/// // Other threads might be pinned, so a single call isn't guaranteed to reclaim everything.
+/// let mut reclaimed = 0;
+/// while reclaimed < 100 {
+///     reclaimed += epoch::force_reclaim_all();
+/// }
+/// ```
+pub fn force_reclaim_all() -> usize {
+    HARNESS.with(|harness| {
+        let was_pinned = harness.is_pinned.get();
+
+        // Just like in `try_reclaim`, count the garbage collected by pinning, too.
+        let before = garbage::destroyed();
+
+        pin(|scope| {
+            scope.push_bag();
+            if !was_pinned {
+                advance_fully(unsafe { &*harness.thread }, scope);
+            }
+            garbage::collect_all(scope);
+        });
+
+        garbage::destroyed().wrapping_sub(before)
+    })
+}
+
 /// Creates an unprotected scope without pinning the current thread.
 pub unsafe fn unprotected<F, T>(f: F) -> T
 where
@@ -420,6 +879,55 @@ where
     f(pin)
 }
 
+/// Frees the object pointed to by `ptr` immediately, without deferring.
+///
+/// This is the counterpart of [`Scope::defer_free`] for code that has exclusive access to the
+/// object, typically a `Drop` impl that walks its nodes in an [`unprotected`] scope. An
+/// unprotected scope has no thread-local buffer, so objects can't be deferred from it. But since
+/// no other thread can be observing the object, there is nothing to wait for anyway. Just like
+/// with `defer_free`, the destructor of the object is not run.
+///
+/// [`Scope::defer_free`]: struct.Scope.html#method.defer_free
+/// [`unprotected`]: fn.unprotected.html
+///
+/// # Safety
+///
+/// The object must have been allocated by [`Owned`], and no other thread may be holding a
+/// reference to it, pinned or not. Null pointers are ignored.
+///
+/// [`Owned`]: struct.Owned.html
+///
+/// # Examples
+///
+/// ```
+/// use coco::epoch::{self, Atomic, Ptr};
+/// use std::sync::atomic::Ordering::Relaxed;
+///
+/// struct Cell {
+///     value: Atomic<u64>,
+/// }
+///
+/// impl Drop for Cell {
+///     fn drop(&mut self) {
+///         // We own the cell, so nobody else can be reading the value.
+///         unsafe {
+///             epoch::unprotected(|scope| {
+///                 let p = self.value.swap(Ptr::null(), Relaxed, scope);
+///                 epoch::defer_free_unprotected(p);
+///             })
+///         }
+///     }
+/// }
+///
+/// drop(Cell { value: Atomic::new(7) });
+/// ```
+pub unsafe fn defer_free_unprotected<T>(ptr: Ptr<T>) {
+    let raw = ptr.as_raw() as *mut T;
+    if !raw.is_null() {
+        free(raw, 1);
+    }
+}
+
 /// Returns `true` if the current thread is pinned.
 #[inline]
 pub fn is_pinned() -> bool {
@@ -429,11 +937,15 @@ pub fn is_pinned() -> bool {
 #[cfg(test)]
 mod tests {
     use std::thread;
-    use std::sync::atomic::Ordering::SeqCst;
+    use std::time::{Duration, Instant};
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT};
+    use std::sync::atomic::Ordering::{Relaxed, SeqCst};
 
-    use epoch::{self, Owned};
+    use epoch::{self, Atomic, DeferResult, NodePool, Owned, Ptr};
     use epoch::garbage::EPOCH;
-    use epoch::thread::{HARNESS, try_advance};
+    use epoch::garbage;
+    use epoch::thread::{ALLOCATED, BAG_CAPACITY, HARNESS, try_advance};
 
     #[test]
     fn pin_reentrant() {
@@ -505,4 +1017,411 @@ mod tests {
             t.join().unwrap();
         }
     }
+
+    #[test]
+    fn reuse_entries() {
+        const THREADS: usize = 2000;
+
+        let before = ALLOCATED.load(Relaxed);
+        for _ in 0..THREADS {
+            thread::spawn(|| epoch::pin(|_| ())).join().unwrap();
+        }
+        let after = ALLOCATED.load(Relaxed);
+
+        // Threads from other tests might be running concurrently, so a few new entries may still
+        // get allocated. But each of the short-lived threads should mostly reuse vacant entries.
+        assert!(after - before < THREADS / 10);
+    }
+
+    #[test]
+    fn try_reclaim() {
+        const COUNT: usize = 10_000;
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Elem(i32);
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, SeqCst);
+            }
+        }
+
+        epoch::pin(|scope| unsafe {
+            for i in 0..COUNT {
+                let a = Owned::new(Elem(i as i32)).into_ptr(scope);
+                scope.defer_drop(a);
+            }
+        });
+
+        // Other threads may help reclaiming our garbage, so the total can't be compared exactly.
+        let mut reclaimed = 0;
+        while DROPS.load(SeqCst) < COUNT {
+            reclaimed += epoch::try_reclaim();
+        }
+        assert!(reclaimed > 0);
+        assert_eq!(DROPS.load(SeqCst), COUNT);
+    }
+
+    #[test]
+    fn force_reclaim_all() {
+        const COUNT: usize = 10_000;
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Elem(i32);
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, SeqCst);
+            }
+        }
+
+        // The participant producing garbage exits before reclamation.
+        thread::spawn(|| {
+            epoch::pin(|scope| unsafe {
+                for i in 0..COUNT {
+                    let a = Owned::new(Elem(i as i32)).into_ptr(scope);
+                    scope.defer_drop(a);
+                }
+            });
+        }).join().unwrap();
+
+        // Threads from other tests may be pinned and hold back the epoch for a little while.
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while DROPS.load(SeqCst) < COUNT {
+            assert!(Instant::now() < deadline, "garbage is not being reclaimed");
+            epoch::force_reclaim_all();
+            thread::yield_now();
+        }
+        assert_eq!(DROPS.load(SeqCst), COUNT);
+
+        // While pinned, nothing new expires, but the call still works.
+        epoch::pin(|_| epoch::force_reclaim_all());
+    }
+
+    #[test]
+    fn force_reclaim_all_many_bags() {
+        // With the `strict_gc` feature this is tens of thousands of bags.
+        const COUNT: usize = 100_000;
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Elem;
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, SeqCst);
+            }
+        }
+
+        thread::spawn(|| {
+            epoch::pin(|scope| unsafe {
+                for _ in 0..COUNT {
+                    scope.defer_drop(Owned::new(Elem).into_ptr(scope));
+                }
+            });
+        }).join().unwrap();
+
+        // Destroying all the expired bags at once must not overflow the default stack, so don't
+        // rely on `RUST_MIN_STACK` and set the size explicitly.
+        thread::Builder::new().stack_size(2 << 20).spawn(|| {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while DROPS.load(SeqCst) < COUNT {
+                assert!(Instant::now() < deadline, "garbage is not being reclaimed");
+                epoch::force_reclaim_all();
+                thread::yield_now();
+            }
+        }).unwrap().join().unwrap();
+        assert_eq!(DROPS.load(SeqCst), COUNT);
+    }
+
+    #[test]
+    fn busy_thread_collects() {
+        const LIMIT: usize = 100 * garbage::MAX_OBJECTS;
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Elem;
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, SeqCst);
+            }
+        }
+
+        // An idle participant that never produces garbage nor helps collect it.
+        let (tx, rx) = mpsc::channel::<()>();
+        let idle = thread::spawn(move || {
+            epoch::pin(|_| {});
+            rx.recv().unwrap_err();
+        });
+
+        // The busy thread never calls into the collector explicitly, yet its garbage gets
+        // reclaimed within a bounded number of its own operations.
+        let ops = thread::spawn(|| {
+            for i in 0..LIMIT {
+                if DROPS.load(SeqCst) > 0 {
+                    return i;
+                }
+                epoch::pin(|scope| unsafe {
+                    scope.defer_drop(Owned::new(Elem).into_ptr(scope));
+                });
+            }
+            LIMIT
+        }).join().unwrap();
+        assert!(ops < LIMIT);
+
+        drop(tx);
+        idle.join().unwrap();
+    }
+
+    #[test]
+    fn defer_recycle() {
+        const COUNT: usize = 100;
+
+        let pool = NodePool::new();
+        let mut raws = epoch::pin(|scope| unsafe {
+            let mut raws = Vec::new();
+            for i in 0..COUNT {
+                let p = pool.alloc(i).into_ptr(scope);
+                raws.push(p.as_raw() as usize);
+                scope.defer_recycle(p, &pool);
+            }
+
+            // The objects may still be referenced while this thread is pinned.
+            assert!(pool.is_empty());
+            raws
+        });
+
+        // The allocations return to the pool only after the epoch has advanced.
+        while pool.len() < COUNT {
+            epoch::force_reclaim_all();
+            thread::yield_now();
+        }
+
+        // New objects reuse the recycled allocations.
+        let reused = (0..COUNT).map(|i| pool.alloc(i)).collect::<Vec<_>>();
+        let mut addrs = reused.iter().map(|o| &**o as *const usize as usize).collect::<Vec<_>>();
+        addrs.sort();
+        raws.sort();
+        assert_eq!(addrs, raws);
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn try_pin() {
+        thread::spawn(|| {
+            assert_eq!(epoch::try_pin(|_| ()), None);
+            assert!(!epoch::is_pinned());
+
+            epoch::pre_register();
+            assert_eq!(epoch::try_pin(|_| epoch::is_pinned()), Some(true));
+
+            // Registering again is a no-op.
+            epoch::pre_register();
+            assert_eq!(epoch::try_pin(|_| 7), Some(7));
+        }).join().unwrap();
+    }
+
+    #[test]
+    fn scope_epoch() {
+        // While this thread is pinned, the global epoch can advance at most once.
+        let before = epoch::pin(|scope| {
+            let e = scope.epoch();
+            assert!((EPOCH.load(SeqCst) >> 1).wrapping_sub(e) <= 1);
+            e
+        });
+
+        // Another thread advances the epoch, which might be held back by threads from other tests.
+        loop {
+            thread::spawn(|| epoch::force_reclaim_all()).join().unwrap();
+
+            let after = epoch::pin(|scope| scope.epoch());
+            if after != before {
+                assert!(after.wrapping_sub(before) < usize::MAX / 2);
+                break;
+            }
+        }
+
+        // An unprotected scope reports the global epoch.
+        let e = unsafe { epoch::unprotected(|scope| scope.epoch()) };
+        assert!((EPOCH.load(SeqCst) >> 1).wrapping_sub(e) < usize::MAX / 2);
+        assert!(e.wrapping_sub(before) < usize::MAX / 2);
+    }
+
+    #[test]
+    fn set_bag_capacity() {
+        const CAP: usize = 3;
+
+        thread::spawn(|| {
+            epoch::set_bag_capacity(CAP);
+            epoch::pin(|scope| unsafe {
+                scope.flush();
+
+                for round in 0..3 {
+                    for i in 0..CAP {
+                        let expected = if round > 0 && i == 0 {
+                            DeferResult::Flushed
+                        } else {
+                            DeferResult::Deferred
+                        };
+                        let a = Owned::new(i).into_ptr(scope);
+                        assert_eq!(scope.defer_free_checked(a), expected);
+                    }
+                }
+            });
+
+            // Capacities are clamped to a sensible range.
+            epoch::set_bag_capacity(0);
+            assert_eq!(BAG_CAPACITY.with(|c| c.get()), 1);
+            epoch::set_bag_capacity(1_000_000);
+            assert_eq!(BAG_CAPACITY.with(|c| c.get()), garbage::MAX_OBJECTS);
+        }).join().unwrap();
+    }
+
+    #[test]
+    fn defer_free_unprotected() {
+        const COUNT: usize = 10;
+
+        struct List {
+            nodes: Vec<Atomic<usize>>,
+        }
+
+        impl Drop for List {
+            fn drop(&mut self) {
+                unsafe {
+                    epoch::unprotected(|scope| {
+                        for a in &self.nodes {
+                            epoch::defer_free_unprotected(a.swap(Ptr::null(), Relaxed, scope));
+                        }
+                    })
+                }
+            }
+        }
+
+        thread::spawn(|| {
+            let bag_len = || HARNESS.with(|h| unsafe { (*h.bag.get()).len() });
+            epoch::pin(|scope| scope.flush());
+            assert_eq!(bag_len(), 0);
+
+            let mut nodes = (0..COUNT).map(Atomic::new).collect::<Vec<_>>();
+            nodes.push(Atomic::null());
+            drop(List { nodes });
+
+            // Nothing was buffered: the objects were freed on the spot.
+            assert_eq!(bag_len(), 0);
+        }).join().unwrap();
+    }
+
+    #[test]
+    fn defer_array() {
+        const LEN: usize = 1000;
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Elem(usize);
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, SeqCst);
+            }
+        }
+
+        let array = (0..LEN).map(Elem).collect::<Vec<_>>().into_boxed_slice();
+        let raw = Box::into_raw(array) as *const Elem;
+
+        epoch::pin(|scope| unsafe {
+            for i in 0..LEN {
+                assert_eq!((*raw.offset(i as isize)).0, i);
+            }
+
+            scope.defer_drop_array(Ptr::from_raw(raw), LEN);
+            scope.flush();
+        });
+
+        while DROPS.load(SeqCst) < LEN {
+            epoch::try_reclaim();
+        }
+        assert_eq!(DROPS.load(SeqCst), LEN);
+    }
+
+    #[test]
+    fn repin() {
+        const COUNT: usize = 10_000;
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Elem(i32);
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, SeqCst);
+            }
+        }
+
+        epoch::pin(|scope| {
+            let t = thread::spawn(|| {
+                epoch::pin(|scope| unsafe {
+                    for i in 0..COUNT {
+                        let a = Owned::new(Elem(i as i32)).into_ptr(scope);
+                        scope.defer_drop(a);
+                    }
+                    scope.flush();
+                });
+
+                while DROPS.load(SeqCst) < COUNT {
+                    epoch::try_reclaim();
+                }
+            });
+
+            // Without repinning, this thread would block reclamation of the garbage forever.
+            while DROPS.load(SeqCst) < COUNT {
+                unsafe { scope.repin() }
+            }
+            assert!(epoch::is_pinned());
+
+            t.join().unwrap();
+        });
+    }
+
+    #[test]
+    fn defer_free_checked() {
+        epoch::pin(|scope| unsafe {
+            scope.flush();
+
+            let mut results = Vec::new();
+            for i in 0..200 {
+                let a = Owned::new(i).into_ptr(scope);
+                results.push(scope.defer_free_checked(a));
+            }
+
+            assert_eq!(results[0], DeferResult::Deferred);
+            assert!(results.contains(&DeferResult::Flushed));
+        });
+    }
+
+    #[test]
+    fn defer_drop_order() {
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Elem(usize, Arc<Mutex<Vec<usize>>>);
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                self.1.lock().unwrap().push(self.0);
+                DROPS.fetch_add(1, SeqCst);
+            }
+        }
+
+        // Defer a few objects into a single buffer, which gets destroyed by one thread.
+        let order = Arc::new(Mutex::new(Vec::new()));
+        epoch::pin(|scope| unsafe {
+            scope.flush();
+            for i in 0..3 {
+                let a = Owned::new(Elem(i, order.clone())).into_ptr(scope);
+                scope.defer_drop(a);
+            }
+            scope.flush();
+        });
+
+        while DROPS.load(SeqCst) < 3 {
+            epoch::try_reclaim();
+        }
+        assert_eq!(*order.lock().unwrap(), vec![0, 1, 2]);
+    }
 }
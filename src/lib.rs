@@ -15,6 +15,8 @@
 //!
 //! * [`Stack`]: A lock-free stack.
 //! * [`deque`]: A lock-free work-stealing deque.
+//! * [`PriorityQueue`]: A lock-free priority queue.
+//! * [`spsc`]: A bounded single-producer single-consumer queue.
 //!
 //! # Which collection should you use?
 //!
@@ -29,6 +31,17 @@
 //! * You want one thread inserting and removing objects, and multiple threads just removing them.
 //! * You don't care about the order of elements.
 //!
+//! ### Use a [`PriorityQueue`] when:
+//!
+//! * You want a shared collection where the smallest object is always removed first.
+//! * Your objects are cheap to clone, since removing an object yields a clone of it.
+//!
+//! ### Use a [`spsc`] queue when:
+//!
+//! * You want exactly one thread inserting objects and exactly one thread removing them.
+//! * You want the first-in first-out order of elements.
+//! * You know the maximum number of objects in the queue in advance.
+//!
 //! # Garbage collection
 //!
 //! An interesting problem concurrent collections deal with comes from the remove operation.
@@ -52,8 +65,10 @@
 //!
 //! [`Stack`]: stack/struct.Stack.html
 //! [`deque`]: deque/fn.new.html
+//! [`PriorityQueue`]: queue/struct.PriorityQueue.html
+//! [`spsc`]: spsc/fn.channel.html
 
-#![cfg_attr(feature = "nightly", feature(const_fn))]
+#![cfg_attr(feature = "nightly", feature(dropck_eyepatch))]
 
 extern crate either;
 
@@ -62,6 +77,11 @@ extern crate scopeguard;
 
 pub mod deque;
 pub mod epoch;
+pub mod queue;
+pub mod spsc;
 pub mod stack;
 
+/// Typical cache line size in bytes on modern machines.
+const CACHE_LINE_BYTES: usize = 64;
+
 pub use stack::Stack;
@@ -0,0 +1,547 @@
+//! Concurrent queues.
+//!
+//! This module offers [`PriorityQueue`], a lock-free priority queue based on a skip list.
+//!
+//! [`PriorityQueue`]: struct.PriorityQueue.html
+
+use std::cell::Cell;
+use std::cmp;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed, Release};
+
+use epoch::{self, Atomic, Owned, Ptr, Scope};
+
+/// Maximum height of a tower in the skip list.
+const MAX_HEIGHT: usize = 32;
+
+/// Returns a random tower height, where each additional level is half as likely as the previous.
+fn random_height() -> usize {
+    thread_local! {
+        /// State of the thread-local xorshift generator. Zero means it isn't seeded yet.
+        static SEED: Cell<u32> = Cell::new(0);
+    }
+
+    SEED.with(|seed| {
+        let mut x = seed.get();
+        if x == 0 {
+            // Seed the generator with the address of the thread-local, which differs per thread.
+            x = seed as *const _ as usize as u32 | 1;
+        }
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        seed.set(x);
+
+        cmp::min(x.trailing_zeros() as usize + 1, MAX_HEIGHT)
+    })
+}
+
+/// A single node in a skip list.
+struct Node<T> {
+    /// The payload.
+    value: T,
+    /// Set once all levels of the tower are linked. Only then is the node allowed to be removed.
+    ready: AtomicBool,
+    /// The next nodes in each level the node is linked into. If the tag is 1, the node is removed
+    /// in that level and can be unlinked from it.
+    tower: Vec<Atomic<Node<T>>>,
+}
+
+/// A position in the skip list, found by searching for some value.
+struct Position<'scope, T: 'scope> {
+    /// In each level, the pointer that points to the first node with a value not less than the
+    /// searched one.
+    preds: [&'scope Atomic<Node<T>>; MAX_HEIGHT],
+    /// In each level, the first node with a value not less than the searched one.
+    succs: [Ptr<'scope, Node<T>>; MAX_HEIGHT],
+}
+
+/// A lock-free priority queue.
+///
+/// Elements are kept sorted in a skip list, so the minimum element is always at the front. It can
+/// be used with multiple producers and multiple consumers at the same time.
+///
+/// Concurrent operations may still be comparing elements against one that was just popped, so
+/// [`pop_min`] returns a clone of the smallest element and leaves the original to be destroyed by
+/// the garbage collector.
+///
+/// # Thread safety
+///
+/// `PriorityQueue<T>` is `Send` whenever `T` is `Send`. Sharing it between threads requires
+/// `T: Send + Sync`, because every operation compares the elements in the skip list by reference,
+/// possibly while other threads are comparing the same elements.
+///
+/// ```
+/// use coco::queue::PriorityQueue;
+///
+/// fn assert_send<T: Send>(_: &T) {}
+/// fn assert_sync<T: Sync>(_: &T) {}
+///
+/// let q = PriorityQueue::new();
+/// q.push(1);
+/// assert_send(&q);
+/// assert_sync(&q);
+/// ```
+///
+/// A queue of values that can be sent but not shared is not `Sync`:
+///
+/// ```compile_fail
+/// use coco::queue::PriorityQueue;
+/// use std::cell::RefCell;
+///
+/// fn assert_sync<T: Sync>(_: &T) {}
+///
+/// let q = PriorityQueue::<RefCell<i32>>::new();
+/// assert_sync(&q);
+/// ```
+///
+/// ```compile_fail
+/// use coco::queue::PriorityQueue;
+/// use std::rc::Rc;
+///
+/// fn assert_send<T: Send>(_: &T) {}
+///
+/// let q = PriorityQueue::<Rc<i32>>::new();
+/// assert_send(&q);
+/// ```
+///
+/// [`pop_min`]: struct.PriorityQueue.html#method.pop_min
+pub struct PriorityQueue<T> {
+    head: [Atomic<Node<T>>; MAX_HEIGHT],
+}
+
+unsafe impl<T: Send> Send for PriorityQueue<T> {}
+unsafe impl<T: Send + Sync> Sync for PriorityQueue<T> {}
+
+impl<T: Ord> PriorityQueue<T> {
+    /// Returns a new, empty priority queue.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::queue::PriorityQueue;
+    ///
+    /// let q = PriorityQueue::<i32>::new();
+    /// ```
+    pub fn new() -> Self {
+        PriorityQueue { head: Default::default() }
+    }
+
+    /// Returns `true` if the priority queue is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::queue::PriorityQueue;
+    ///
+    /// let q = PriorityQueue::new();
+    /// assert!(q.is_empty());
+    /// q.push(1);
+    /// assert!(!q.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        epoch::pin(|scope| {
+            let mut curr = self.head[0].load(Acquire, scope);
+            while let Some(c) = unsafe { curr.as_ref() } {
+                let succ = c.tower[0].load(Acquire, scope);
+                if succ.tag() == 0 {
+                    return false;
+                }
+                curr = succ.with_tag(0);
+            }
+            true
+        })
+    }
+
+    /// Pushes a new value into the priority queue.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::queue::PriorityQueue;
+    ///
+    /// let q = PriorityQueue::new();
+    /// q.push(2);
+    /// q.push(1);
+    /// ```
+    pub fn push(&self, value: T) {
+        let height = random_height();
+        let node = Owned::new(Node {
+            value,
+            ready: AtomicBool::new(false),
+            tower: (0..height).map(|_| Atomic::null()).collect(),
+        });
+
+        epoch::pin(|scope| {
+            let node = node.into_ptr(scope);
+            let n = unsafe { node.deref() };
+
+            // Link the tower level by level, starting from the bottom. The node is reachable as
+            // soon as it gets linked into the bottom level.
+            let mut pos = self.search(&n.value, scope);
+            for level in 0..height {
+                loop {
+                    n.tower[level].store(pos.succs[level], Relaxed);
+                    let pred = pos.preds[level];
+                    if pred.compare_and_swap(pos.succs[level], node, AcqRel, scope).is_ok() {
+                        break;
+                    }
+                    // The position has changed. Search for it again.
+                    pos = self.search(&n.value, scope);
+                }
+            }
+
+            // The tower is complete. From now on the node may be popped.
+            n.ready.store(true, Release);
+        })
+    }
+
+    /// Attempts to pop the smallest value from the priority queue.
+    ///
+    /// Returns `None` if the priority queue is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::queue::PriorityQueue;
+    ///
+    /// let q = PriorityQueue::new();
+    /// q.push(2);
+    /// q.push(3);
+    /// q.push(1);
+    /// assert_eq!(q.pop_min(), Some(1));
+    /// assert_eq!(q.pop_min(), Some(2));
+    /// assert_eq!(q.pop_min(), Some(3));
+    /// assert_eq!(q.pop_min(), None);
+    /// ```
+    pub fn pop_min(&self) -> Option<T>
+    where
+        T: Clone + Send + 'static,
+    {
+        epoch::pin(|scope| {
+            let mut curr = self.head[0].load(Acquire, scope);
+            loop {
+                let c = match unsafe { curr.as_ref() } {
+                    None => return None,
+                    Some(c) => c,
+                };
+                let succ = c.tower[0].load(Acquire, scope);
+
+                // Skip nodes that are already removed or still being linked.
+                if succ.tag() == 1 || !c.ready.load(Acquire) {
+                    curr = succ.with_tag(0);
+                    continue;
+                }
+
+                // Try removing the node by marking it in the bottom level. If that fails, the
+                // node either got removed or got a new successor, so examine it again.
+                if c.tower[0].compare_and_swap(succ, succ.with_tag(1), AcqRel, scope).is_ok() {
+                    // Even if cloning panics, the node must be unlinked and destroyed.
+                    defer! {{
+                        // Mark the rest of the tower so that the node gets unlinked in every level.
+                        for level in 1..c.tower.len() {
+                            c.tower[level].fetch_or(1, AcqRel, scope);
+                        }
+                        self.unlink(curr, scope);
+                        unsafe { scope.defer_drop(curr) }
+                    }}
+                    return Some(c.value.clone());
+                }
+            }
+        })
+    }
+
+    /// Searches for the position of `value`, unlinking removed nodes along the way.
+    fn search<'scope>(&'scope self, value: &T, scope: &'scope Scope) -> Position<'scope, T> {
+        'search: loop {
+            let mut pos = Position {
+                preds: [&self.head[0]; MAX_HEIGHT],
+                succs: [Ptr::null(); MAX_HEIGHT],
+            };
+
+            // The tower of the last node with a value less than `value`.
+            let mut pred: &'scope [Atomic<Node<T>>] = &self.head;
+
+            for level in (0..MAX_HEIGHT).rev() {
+                let mut curr = pred[level].load(Acquire, scope);
+
+                // If the predecessor got removed in this level, start over.
+                if curr.tag() == 1 {
+                    continue 'search;
+                }
+
+                while let Some(c) = unsafe { curr.as_ref() } {
+                    let succ = c.tower[level].load(Acquire, scope);
+
+                    if succ.tag() == 1 {
+                        // This node is removed. Try unlinking it from this level.
+                        match pred[level].compare_and_swap(curr, succ.with_tag(0), AcqRel, scope) {
+                            Ok(()) => curr = succ.with_tag(0),
+                            Err(_) => continue 'search,
+                        }
+                    } else if c.value < *value {
+                        // Move one step forward.
+                        pred = &c.tower;
+                        curr = succ;
+                    } else {
+                        break;
+                    }
+                }
+
+                pos.preds[level] = &pred[level];
+                pos.succs[level] = curr;
+            }
+
+            return pos;
+        }
+    }
+
+    /// Unlinks a removed `node` from every level of the skip list.
+    ///
+    /// The node must be marked in every level of its tower.
+    fn unlink(&self, node: Ptr<Node<T>>, scope: &Scope) {
+        let n = unsafe { node.deref() };
+
+        'search: loop {
+            let pos = self.search(&n.value, scope);
+
+            // The node is somewhere in the run of values equal to its own in every level, so
+            // scan the run until the node is unlinked.
+            for level in 0..n.tower.len() {
+                let mut pred = pos.preds[level];
+                let mut curr = pos.succs[level];
+
+                while let Some(c) = unsafe { curr.as_ref() } {
+                    let succ = c.tower[level].load(Acquire, scope);
+
+                    if succ.tag() == 1 {
+                        if pred.compare_and_swap(curr, succ.with_tag(0), AcqRel, scope).is_err() {
+                            continue 'search;
+                        }
+                        if curr.as_raw() == node.as_raw() {
+                            break;
+                        }
+                        curr = succ.with_tag(0);
+                    } else if c.value > n.value {
+                        break;
+                    } else {
+                        pred = &c.tower[level];
+                        curr = succ;
+                    }
+                }
+            }
+
+            return;
+        }
+    }
+}
+
+impl<T: Ord> Default for PriorityQueue<T> {
+    fn default() -> Self {
+        PriorityQueue::new()
+    }
+}
+
+impl<T> Drop for PriorityQueue<T> {
+    fn drop(&mut self) {
+        // Destruct all nodes in the bottom level, which contains every node in the skip list.
+        unsafe {
+            epoch::unprotected(|scope| {
+                let mut curr = self.head[0].load(Relaxed, scope).as_raw();
+                while !curr.is_null() {
+                    let node = Box::from_raw(curr as *mut Node<T>);
+                    curr = node.tower[0].load(Relaxed, scope).as_raw();
+                    drop(node);
+                }
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+
+    use std::cmp::Ordering;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use super::PriorityQueue;
+    use self::rand::Rng;
+
+    #[test]
+    fn smoke() {
+        let q = PriorityQueue::new();
+        q.push(1);
+        assert_eq!(q.pop_min(), Some(1));
+        assert_eq!(q.pop_min(), None);
+    }
+
+    #[test]
+    fn push_pop() {
+        let q = PriorityQueue::new();
+        q.push(3);
+        q.push(1);
+        q.push(2);
+        assert_eq!(q.pop_min(), Some(1));
+        q.push(0);
+        assert_eq!(q.pop_min(), Some(0));
+        assert_eq!(q.pop_min(), Some(2));
+        assert_eq!(q.pop_min(), Some(3));
+        assert_eq!(q.pop_min(), None);
+        q.push(5);
+        assert_eq!(q.pop_min(), Some(5));
+        assert_eq!(q.pop_min(), None);
+    }
+
+    #[test]
+    fn duplicates() {
+        let q = PriorityQueue::new();
+        for i in 0..100 {
+            q.push(i % 10);
+        }
+        for i in 0..100 {
+            assert_eq!(q.pop_min(), Some(i / 10));
+        }
+        assert_eq!(q.pop_min(), None);
+    }
+
+    #[test]
+    fn is_empty() {
+        let q = PriorityQueue::new();
+        assert!(q.is_empty());
+
+        for i in 0..3 {
+            q.push(i);
+            assert!(!q.is_empty());
+        }
+
+        for _ in 0..3 {
+            assert!(!q.is_empty());
+            q.pop_min();
+        }
+
+        assert!(q.is_empty());
+        q.push(3);
+        assert!(!q.is_empty());
+        q.pop_min();
+        assert!(q.is_empty());
+    }
+
+    #[test]
+    fn sorted() {
+        const COUNT: usize = 10_000;
+
+        let q = PriorityQueue::new();
+        let mut rng = rand::thread_rng();
+        for _ in 0..COUNT {
+            q.push(rng.gen_range(0, COUNT / 10));
+        }
+
+        let mut last = 0;
+        for _ in 0..COUNT {
+            let x = q.pop_min().unwrap();
+            assert!(last <= x);
+            last = x;
+        }
+        assert_eq!(q.pop_min(), None);
+    }
+
+    #[test]
+    fn stress() {
+        const THREADS: usize = 8;
+        const COUNT: usize = 20_000;
+
+        let q = Arc::new(PriorityQueue::new());
+        let popped = Arc::new(Mutex::new(Vec::new()));
+
+        let threads = (0..THREADS).map(|t| {
+            let q = q.clone();
+            let popped = popped.clone();
+
+            thread::spawn(move || {
+                let mut rng = rand::thread_rng();
+                let mut mine = Vec::new();
+                for i in 0..COUNT {
+                    q.push(t + THREADS * i);
+                    if rng.gen_range(0, 2) == 0 {
+                        if let Some(x) = q.pop_min() {
+                            mine.push(x);
+                        }
+                    }
+                }
+                popped.lock().unwrap().extend(mine);
+            })
+        }).collect::<Vec<_>>();
+
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        // Once operations are serialized, pops must come out in nondecreasing order.
+        let mut popped = popped.lock().unwrap();
+        let mut last = None;
+        while let Some(x) = q.pop_min() {
+            assert!(last <= Some(x));
+            last = Some(x);
+            popped.push(x);
+        }
+
+        // Every pushed value must have been popped exactly once.
+        popped.sort();
+        assert_eq!(*popped, (0..THREADS * COUNT).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn destructors() {
+        #[derive(Clone)]
+        struct Elem(usize, Arc<Mutex<Vec<usize>>>);
+
+        impl PartialEq for Elem {
+            fn eq(&self, other: &Elem) -> bool {
+                self.0 == other.0
+            }
+        }
+
+        impl Eq for Elem {}
+
+        impl PartialOrd for Elem {
+            fn partial_cmp(&self, other: &Elem) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for Elem {
+            fn cmp(&self, other: &Elem) -> Ordering {
+                self.0.cmp(&other.0)
+            }
+        }
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                self.1.lock().unwrap().push(self.0);
+            }
+        }
+
+        const COUNT: usize = 1000;
+
+        let dropped = Arc::new(Mutex::new(Vec::new()));
+        let q = PriorityQueue::new();
+
+        for i in 0..COUNT {
+            q.push(Elem(i, dropped.clone()));
+        }
+        for i in 0..COUNT / 2 {
+            assert_eq!(q.pop_min().unwrap().0, i);
+        }
+
+        // Popped values are only ever cloned, while the rest must not have been dropped yet.
+        assert!(dropped.lock().unwrap().iter().all(|&i| i < COUNT / 2));
+        drop(q);
+
+        let mut rest = dropped.lock().unwrap().iter().cloned().filter(|&i| i >= COUNT / 2)
+            .collect::<Vec<_>>();
+        rest.sort();
+        assert_eq!(rest, (COUNT / 2..COUNT).collect::<Vec<_>>());
+    }
+}
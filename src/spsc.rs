@@ -0,0 +1,454 @@
+//! A bounded single-producer single-consumer queue.
+//!
+//! This is a ring buffer with a fixed capacity. There is exactly one producer and one consumer,
+//! so the queue doesn't need any compare-and-swap operations nor garbage collection: the producer
+//! owns the tail index, the consumer owns the head index, and each side only reads the other's
+//! index with acquire ordering.
+//!
+//! # Examples
+//!
+//! ```
+//! use coco::spsc;
+//! use std::thread;
+//!
+//! let (p, c) = spsc::channel(16);
+//!
+//! let t = thread::spawn(move || {
+//!     for i in 0..1000 {
+//!         let mut value = i;
+//!         while let Err(v) = p.push(value) {
+//!             value = v;
+//!         }
+//!     }
+//! });
+//!
+//! for i in 0..1000 {
+//!     loop {
+//!         if let Some(x) = c.pop() {
+//!             assert_eq!(x, i);
+//!             break;
+//!         }
+//!     }
+//! }
+//!
+//! t.join().unwrap();
+//! ```
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr;
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+
+use CACHE_LINE_BYTES;
+
+/// The ring buffer shared by the producer and the consumer.
+///
+/// Indices are kept in the range `0 .. 2 * cap`. That way a full buffer (the indices are `cap`
+/// apart) can be distinguished from an empty one (the indices are equal).
+#[repr(C)]
+struct Inner<T> {
+    /// The index of the next slot to pop from. Only the consumer modifies it.
+    head: AtomicUsize,
+
+    /// Some padding to avoid false sharing.
+    _pad0: [u8; CACHE_LINE_BYTES],
+
+    /// The index of the next slot to push into. Only the producer modifies it.
+    tail: AtomicUsize,
+
+    /// Some padding to avoid false sharing.
+    _pad1: [u8; CACHE_LINE_BYTES],
+
+    /// Pointer to the allocated memory.
+    buffer: *mut T,
+
+    /// Capacity of the buffer.
+    cap: usize,
+}
+
+impl<T> Inner<T> {
+    /// Returns the index that comes after `index`.
+    fn next(&self, index: usize) -> usize {
+        if index + 1 == 2 * self.cap {
+            0
+        } else {
+            index + 1
+        }
+    }
+
+    /// Returns a pointer to the slot at the specified `index`.
+    unsafe fn at(&self, index: usize) -> *mut T {
+        let slot = if index >= self.cap { index - self.cap } else { index };
+        self.buffer.add(slot)
+    }
+
+    /// Returns the number of elements between `head` and `tail`.
+    fn distance(&self, head: usize, tail: usize) -> usize {
+        if tail >= head {
+            tail - head
+        } else {
+            tail + 2 * self.cap - head
+        }
+    }
+
+    /// Returns the number of elements in the buffer.
+    fn len(&self) -> usize {
+        let head = self.head.load(Acquire);
+        let tail = self.tail.load(Acquire);
+        self.distance(head, tail)
+    }
+}
+
+impl<T> Drop for Inner<T> {
+    fn drop(&mut self) {
+        let tail = self.tail.load(Relaxed);
+        let mut head = self.head.load(Relaxed);
+
+        unsafe {
+            // Drop all elements still in the buffer.
+            while head != tail {
+                ptr::drop_in_place(self.at(head));
+                head = self.next(head);
+            }
+
+            // Free the memory allocated by the buffer.
+            drop(Vec::from_raw_parts(self.buffer, 0, self.cap));
+        }
+    }
+}
+
+/// The producer side of a single-producer single-consumer queue.
+pub struct Producer<T> {
+    inner: Arc<Inner<T>>,
+    _marker: PhantomData<*mut ()>, // !Send + !Sync
+}
+
+unsafe impl<T: Send> Send for Producer<T> {}
+
+impl<T> Producer<T> {
+    /// Attempts to push `value` into the queue.
+    ///
+    /// If the queue is full, the value is returned back as an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::spsc;
+    ///
+    /// let (p, c) = spsc::channel(1);
+    /// assert_eq!(p.push(1), Ok(()));
+    /// assert_eq!(p.push(2), Err(2));
+    /// assert_eq!(c.pop(), Some(1));
+    /// assert_eq!(p.push(2), Ok(()));
+    /// ```
+    pub fn push(&self, value: T) -> Result<(), T> {
+        let inner = &*self.inner;
+        let tail = inner.tail.load(Relaxed);
+        let head = inner.head.load(Acquire);
+
+        // Is the queue full?
+        if inner.distance(head, tail) == inner.cap {
+            return Err(value);
+        }
+
+        // Write `value` into the slot and publish it to the consumer.
+        unsafe { ptr::write(inner.at(tail), value) }
+        inner.tail.store(inner.next(tail), Release);
+        Ok(())
+    }
+
+    /// Returns the number of elements in the queue.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::spsc;
+    ///
+    /// let (p, _c) = spsc::channel(8);
+    /// p.push(1).unwrap();
+    /// assert_eq!(p.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns `true` if the queue is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::spsc;
+    ///
+    /// let (p, _c) = spsc::channel(8);
+    /// assert!(p.is_empty());
+    /// p.push(1).unwrap();
+    /// assert!(!p.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.inner.len() == 0
+    }
+
+    /// Returns the capacity of the queue.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::spsc;
+    ///
+    /// let (p, _c) = spsc::channel::<i32>(8);
+    /// assert_eq!(p.capacity(), 8);
+    /// ```
+    pub fn capacity(&self) -> usize {
+        self.inner.cap
+    }
+}
+
+impl<T> fmt::Debug for Producer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Producer {{ ... }}")
+    }
+}
+
+/// The consumer side of a single-producer single-consumer queue.
+pub struct Consumer<T> {
+    inner: Arc<Inner<T>>,
+    _marker: PhantomData<*mut ()>, // !Send + !Sync
+}
+
+unsafe impl<T: Send> Send for Consumer<T> {}
+
+impl<T> Consumer<T> {
+    /// Attempts to pop a value from the queue.
+    ///
+    /// Returns `None` if the queue is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::spsc;
+    ///
+    /// let (p, c) = spsc::channel(8);
+    /// p.push(1).unwrap();
+    /// p.push(2).unwrap();
+    /// assert_eq!(c.pop(), Some(1));
+    /// assert_eq!(c.pop(), Some(2));
+    /// assert_eq!(c.pop(), None);
+    /// ```
+    pub fn pop(&self) -> Option<T> {
+        let inner = &*self.inner;
+        let head = inner.head.load(Relaxed);
+        let tail = inner.tail.load(Acquire);
+
+        // Is the queue empty?
+        if head == tail {
+            return None;
+        }
+
+        // Read the value from the slot and hand the slot back to the producer.
+        let value = unsafe { ptr::read(inner.at(head)) };
+        inner.head.store(inner.next(head), Release);
+        Some(value)
+    }
+
+    /// Returns the number of elements in the queue.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::spsc;
+    ///
+    /// let (p, c) = spsc::channel(8);
+    /// p.push(1).unwrap();
+    /// assert_eq!(c.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns `true` if the queue is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::spsc;
+    ///
+    /// let (p, c) = spsc::channel(8);
+    /// assert!(c.is_empty());
+    /// p.push(1).unwrap();
+    /// assert!(!c.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.inner.len() == 0
+    }
+
+    /// Returns the capacity of the queue.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::spsc;
+    ///
+    /// let (_p, c) = spsc::channel::<i32>(8);
+    /// assert_eq!(c.capacity(), 8);
+    /// ```
+    pub fn capacity(&self) -> usize {
+        self.inner.cap
+    }
+}
+
+impl<T> fmt::Debug for Consumer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Consumer {{ ... }}")
+    }
+}
+
+/// Returns a new single-producer single-consumer queue with capacity `cap`.
+///
+/// # Panics
+///
+/// Panics if `cap` is zero or greater than `usize::MAX / 2`.
+///
+/// # Examples
+///
+/// ```
+/// use coco::spsc;
+///
+/// let (p, c) = spsc::channel(8);
+/// p.push(1).unwrap();
+/// assert_eq!(c.pop(), Some(1));
+/// ```
+pub fn channel<T>(cap: usize) -> (Producer<T>, Consumer<T>) {
+    assert!(cap > 0, "capacity must be non-zero");
+    // Indices wrap around at `2 * cap`, which must not overflow.
+    assert!(cap <= usize::MAX / 2, "capacity overflow: {} > usize::MAX / 2", cap);
+
+    let mut v = Vec::with_capacity(cap);
+    let buffer = v.as_mut_ptr();
+    mem::forget(v);
+
+    let inner = Arc::new(Inner {
+        head: AtomicUsize::new(0),
+        _pad0: [0; CACHE_LINE_BYTES],
+        tail: AtomicUsize::new(0),
+        _pad1: [0; CACHE_LINE_BYTES],
+        buffer,
+        cap,
+    });
+
+    let p = Producer {
+        inner: inner.clone(),
+        _marker: PhantomData,
+    };
+    let c = Consumer {
+        inner,
+        _marker: PhantomData,
+    };
+    (p, c)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic;
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::thread;
+
+    #[test]
+    fn smoke() {
+        let (p, c) = super::channel(1);
+        assert_eq!(c.pop(), None);
+        assert_eq!(p.push(1), Ok(()));
+        assert_eq!(p.push(2), Err(2));
+        assert_eq!(c.pop(), Some(1));
+        assert_eq!(c.pop(), None);
+    }
+
+    #[test]
+    fn capacity_overflow() {
+        assert!(panic::catch_unwind(|| super::channel::<u8>(usize::MAX / 2 + 1)).is_err());
+        assert!(panic::catch_unwind(|| super::channel::<u8>(usize::MAX)).is_err());
+    }
+
+    #[test]
+    fn push_pop() {
+        const CAP: usize = 3;
+
+        let (p, c) = super::channel(CAP);
+        for i in 0..100 {
+            for j in 0..CAP {
+                assert_eq!(p.push(i * CAP + j), Ok(()));
+            }
+            assert_eq!(p.len(), CAP);
+            assert!(p.push(0).is_err());
+
+            for j in 0..CAP {
+                assert_eq!(c.pop(), Some(i * CAP + j));
+            }
+            assert_eq!(c.len(), 0);
+            assert_eq!(c.pop(), None);
+        }
+    }
+
+    #[test]
+    fn stress() {
+        const COUNT: usize = 1_000_000;
+
+        let (p, c) = super::channel(100);
+
+        let t = thread::spawn(move || {
+            for i in 0..COUNT {
+                let mut value = i;
+                while let Err(v) = p.push(value) {
+                    value = v;
+                    thread::yield_now();
+                }
+            }
+        });
+
+        for i in 0..COUNT {
+            loop {
+                if let Some(x) = c.pop() {
+                    assert_eq!(x, i);
+                    break;
+                }
+                thread::yield_now();
+            }
+        }
+        assert_eq!(c.pop(), None);
+
+        t.join().unwrap();
+    }
+
+    #[test]
+    fn destructors() {
+        struct Elem(Arc<AtomicUsize>);
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, SeqCst);
+            }
+        }
+
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let (p, c) = super::channel(10);
+
+        for _ in 0..10 {
+            assert!(p.push(Elem(dropped.clone())).is_ok());
+        }
+        for _ in 0..4 {
+            drop(c.pop());
+        }
+        for _ in 0..3 {
+            assert!(p.push(Elem(dropped.clone())).is_ok());
+        }
+        assert_eq!(dropped.load(SeqCst), 4);
+
+        drop(p);
+        drop(c);
+        assert_eq!(dropped.load(SeqCst), 13);
+    }
+}
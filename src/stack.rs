@@ -2,10 +2,15 @@
 //!
 //! This is an implementation of the Treiber stack, one of the simplest lock-free data structures.
 
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::mem;
 use std::ptr;
 use std::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed};
+use std::vec;
 
-use epoch::{self, Atomic, Owned};
+use epoch::{self, Atomic, Owned, Ptr, Scope};
+use scopeguard::guard;
 
 /// A single node in a stack.
 struct Node<T> {
@@ -15,11 +20,120 @@ struct Node<T> {
     next: Atomic<Node<T>>,
 }
 
+/// A chain of nodes detached from a stack, whose values are yet to be taken out.
+///
+/// Dropping it drops the remaining values and defers freeing of their nodes.
+struct Detached<'scope, T: 'scope> {
+    /// The first node in the chain.
+    head: Ptr<'scope, Node<T>>,
+    /// The scope the chain was detached in.
+    scope: &'scope Scope,
+}
+
+impl<'scope, T> Detached<'scope, T> {
+    /// Takes the value of the first node out of the chain.
+    fn pop(&mut self) -> Option<T> {
+        unsafe {
+            self.head.as_ref().map(|h| {
+                let value = ptr::read(&h.value);
+                self.scope.defer_free(self.head);
+                self.head = h.next.load(Relaxed, self.scope);
+                value
+            })
+        }
+    }
+}
+
+impl<'scope, T> Drop for Detached<'scope, T> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
 /// A lock-free stack.
 ///
 /// It can be used with multiple producers and multiple consumers at the same time.
+///
+/// Values are stored inline in the nodes, so `T` must be sized. Unsized values like trait objects
+/// or slices can be stored by boxing them. Popping moves the box out of its node, which makes the
+/// caller the sole owner of the boxed value.
+///
+/// # Examples
+///
+/// A stack of heterogeneous closures:
+///
+/// ```
+/// use coco::Stack;
+///
+/// let s: Stack<Box<Fn() -> i32 + Send>> = Stack::new();
+/// s.push(Box::new(|| 1));
+/// s.push(Box::new(|| 2));
+///
+/// assert_eq!(s.pop().map(|f| f()), Some(2));
+/// assert_eq!(s.pop().map(|f| f()), Some(1));
+/// ```
+///
+/// # Thread safety
+///
+/// `Stack<T>` is `Send` and `Sync` whenever `T` is `Send`. Most operations only move values in
+/// and out of the stack, so values are never shared between threads. The few methods that read
+/// values while they are still on the stack, like [`scan`] and [`pop_while`], additionally
+/// require `T: Copy + Sync`.
+///
+/// A stack of values that can be sent but not shared is still `Sync`:
+///
+/// ```
+/// use coco::Stack;
+/// use std::cell::Cell;
+///
+/// fn assert_sync<T: Sync>(_: &T) {}
+///
+/// let s = Stack::new();
+/// s.push(Cell::new(1));
+/// assert_sync(&s);
+/// ```
+///
+/// But a stack of values that cannot even be sent is neither `Send` nor `Sync`:
+///
+/// ```compile_fail
+/// use coco::Stack;
+/// use std::rc::Rc;
+///
+/// fn assert_send<T: Send>(_: &T) {}
+///
+/// let s = Stack::new();
+/// s.push(Rc::new(1));
+/// assert_send(&s);
+/// ```
+///
+/// ```compile_fail
+/// use coco::Stack;
+/// use std::rc::Rc;
+///
+/// fn assert_sync<T: Sync>(_: &T) {}
+///
+/// let s = Stack::new();
+/// s.push(Rc::new(1));
+/// assert_sync(&s);
+/// ```
+///
+/// Values that are not `Sync` cannot be inspected in place:
+///
+/// ```compile_fail
+/// use coco::Stack;
+/// use std::cell::Cell;
+///
+/// let s = Stack::new();
+/// s.push(Cell::new(1));
+/// s.scan((), |_, c| Some(c.get()));
+/// ```
+///
+/// [`scan`]: struct.Stack.html#method.scan
+/// [`pop_while`]: struct.Stack.html#method.pop_while
 pub struct Stack<T> {
     head: Atomic<Node<T>>,
+    /// Tells the drop checker that the stack owns values of type `T`.
+    _marker: PhantomData<T>,
 }
 
 unsafe impl<T: Send> Send for Stack<T> {}
@@ -36,7 +150,10 @@ impl<T> Stack<T> {
     /// let s = Stack::<i32>::new();
     /// ```
     pub fn new() -> Self {
-        Stack { head: Atomic::null() }
+        Stack {
+            head: Atomic::null(),
+            _marker: PhantomData,
+        }
     }
 
     /// Returns `true` if the stack is empty.
@@ -55,6 +172,37 @@ impl<T> Stack<T> {
         epoch::pin(|scope| self.head.load(Acquire, scope).is_null())
     }
 
+    /// Returns `true` if the stack has at least `n` values.
+    ///
+    /// At most `n` nodes are visited, so this is cheap for small thresholds even if the stack is
+    /// large. If other threads are concurrently modifying the stack, the answer is best-effort.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::Stack;
+    ///
+    /// let s = Stack::new();
+    /// s.push(1);
+    /// s.push(2);
+    ///
+    /// assert!(s.len_at_least(0));
+    /// assert!(s.len_at_least(2));
+    /// assert!(!s.len_at_least(3));
+    /// ```
+    pub fn len_at_least(&self, n: usize) -> bool {
+        epoch::pin(|scope| {
+            let mut curr = self.head.load(Acquire, scope);
+            for _ in 0..n {
+                match unsafe { curr.as_ref() } {
+                    None => return false,
+                    Some(c) => curr = c.next.load(Acquire, scope),
+                }
+            }
+            true
+        })
+    }
+
     /// Pushes a new value onto the stack.
     ///
     /// # Examples
@@ -87,6 +235,139 @@ impl<T> Stack<T> {
         })
     }
 
+    /// Pushes a new value onto the stack unless an equal value is already in it.
+    ///
+    /// Returns `true` if the value was pushed.
+    ///
+    /// This walks the whole stack, so it takes linear time. Uniqueness is only best-effort: if
+    /// another thread pushes an equal value at the same time, both values may end up in the stack.
+    ///
+    /// Values are compared while still on the stack, where a concurrent `pop` might be moving them
+    /// out at the same time. That is why this method requires `T: Copy`, just like [`pop_while`].
+    ///
+    /// [`pop_while`]: struct.Stack.html#method.pop_while
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::Stack;
+    ///
+    /// let s = Stack::new();
+    /// assert!(s.push_unique(1));
+    /// assert!(s.push_unique(2));
+    /// assert!(!s.push_unique(1));
+    /// assert_eq!(s.pop(), Some(2));
+    /// assert_eq!(s.pop(), Some(1));
+    /// assert_eq!(s.pop(), None);
+    /// ```
+    pub fn push_unique(&self, value: T) -> bool
+    where
+        T: Copy + PartialEq + Sync,
+    {
+        let mut node = Owned::new(Node {
+            value,
+            next: Atomic::null(),
+        });
+
+        epoch::pin(|scope| {
+            let mut head = self.head.load(Acquire, scope);
+            loop {
+                let mut curr = head;
+                while let Some(c) = unsafe { curr.as_ref() } {
+                    if c.value == node.value {
+                        return false;
+                    }
+                    curr = c.next.load(Acquire, scope);
+                }
+
+                node.next.store(head, Relaxed);
+                match self.head.compare_and_swap_weak_owned(head, node, AcqRel, scope) {
+                    Ok(_) => return true,
+                    Err((h, n)) => {
+                        head = h;
+                        node = n;
+                    }
+                }
+            }
+        })
+    }
+
+    /// Pushes a new value onto the stack, but only if the stack is empty.
+    ///
+    /// If the stack is not empty, the value is returned back as an error. This is useful for
+    /// lazily seeding a stack, when only the first of many racing producers should succeed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::Stack;
+    ///
+    /// let s = Stack::new();
+    /// assert_eq!(s.push_if_empty(1), Ok(()));
+    /// assert_eq!(s.push_if_empty(2), Err(2));
+    /// assert_eq!(s.pop(), Some(1));
+    /// ```
+    pub fn push_if_empty(&self, value: T) -> Result<(), T> {
+        let node = Owned::new(Node {
+            value,
+            next: Atomic::null(),
+        });
+
+        epoch::pin(|scope| {
+            match self.head.compare_and_swap_owned(Ptr::null(), node, AcqRel, scope) {
+                Ok(_) => Ok(()),
+                Err((_, node)) => Err(node.into_box().value),
+            }
+        })
+    }
+
+    /// Makes a single attempt at pushing a new value onto the stack.
+    ///
+    /// This reads the current top and tries to install the new node with one compare-and-swap.
+    /// If another thread changes the top in the meantime, the value is returned back as an error
+    /// instead of retrying. That way callers can build their own retry policies, e.g. with
+    /// exponential backoff or a bounded number of attempts. A plain [`push`] retries immediately
+    /// until it succeeds.
+    ///
+    /// [`push`]: struct.Stack.html#method.push
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::Stack;
+    /// use std::thread;
+    ///
+    /// let s = Stack::new();
+    ///
+    /// // Retry with an increasing number of yields between attempts.
+    /// let mut value = 1;
+    /// let mut backoff = 1;
+    /// while let Err(v) = s.cas_push(value) {
+    ///     value = v;
+    ///     for _ in 0..backoff {
+    ///         thread::yield_now();
+    ///     }
+    ///     backoff *= 2;
+    /// }
+    ///
+    /// assert_eq!(s.pop(), Some(1));
+    /// ```
+    pub fn cas_push(&self, value: T) -> Result<(), T> {
+        let node = Owned::new(Node {
+            value,
+            next: Atomic::null(),
+        });
+
+        epoch::pin(|scope| {
+            let head = self.head.load(Acquire, scope);
+            node.next.store(head, Relaxed);
+            match self.head.compare_and_swap_owned(head, node, AcqRel, scope) {
+                Ok(_) => Ok(()),
+                Err((_, node)) => Err(node.into_box().value),
+            }
+        })
+    }
+
     /// Attempts to pop an value from the stack.
     ///
     /// Returns `None` if the stack is empty.
@@ -123,83 +404,1798 @@ impl<T> Stack<T> {
             }
         })
     }
-}
 
-impl<T> Drop for Stack<T> {
-    fn drop(&mut self) {
-        // Destruct all nodes in the stack.
-        unsafe {
-            epoch::unprotected(|scope| {
-                let mut curr = self.head.load(Relaxed, scope).as_raw();
-                while !curr.is_null() {
-                    let next = (*curr).next.load(Relaxed, scope).as_raw();
-                    drop(Box::from_raw(curr as *mut Node<T>));
-                    curr = next;
+    /// Pops the value from the top of the stack, but only if it is equal to `expected`.
+    ///
+    /// Returns the popped value on success. Otherwise, returns the value currently on the top, or
+    /// `None` if the stack is empty.
+    ///
+    /// The value on the top is compared while still on the stack, where a concurrent `pop` might
+    /// be moving it out at the same time. That is why this method requires `T: Copy`, just like
+    /// [`pop_while`].
+    ///
+    /// [`pop_while`]: struct.Stack.html#method.pop_while
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::Stack;
+    ///
+    /// let s = Stack::new();
+    /// assert_eq!(s.compare_top_and_pop(&1), Err(None));
+    ///
+    /// s.push(1);
+    /// s.push(2);
+    /// assert_eq!(s.compare_top_and_pop(&1), Err(Some(2)));
+    /// assert_eq!(s.compare_top_and_pop(&2), Ok(2));
+    /// assert_eq!(s.compare_top_and_pop(&1), Ok(1));
+    /// ```
+    pub fn compare_top_and_pop(&self, expected: &T) -> Result<T, Option<T>>
+    where
+        T: Copy + PartialEq + Sync,
+    {
+        epoch::pin(|scope| {
+            let mut head = self.head.load(Acquire, scope);
+            loop {
+                match unsafe { head.as_ref() } {
+                    Some(h) => {
+                        if h.value != *expected {
+                            return Err(Some(h.value));
+                        }
+
+                        let next = h.next.load(Acquire, scope);
+                        match self.head.compare_and_swap_weak(head, next, AcqRel, scope) {
+                            Ok(()) => unsafe {
+                                scope.defer_free(head);
+                                return Ok(h.value);
+                            },
+                            Err(h) => head = h,
+                        }
+                    }
+                    None => return Err(None),
                 }
-            })
-        }
+            }
+        })
     }
-}
-
-#[cfg(test)]
-mod tests {
-    extern crate rand;
-
-    use std::sync::Arc;
-    use std::sync::atomic::AtomicUsize;
-    use std::sync::atomic::Ordering::SeqCst;
-    use std::thread;
 
-    use super::Stack;
-    use self::rand::Rng;
+    /// Pops values from the top of the stack for as long as `pred` returns `true`.
+    ///
+    /// The popped values are returned in the order they were popped. The first value for which
+    /// `pred` returns `false` stays on the stack. All the popped values are removed from the stack
+    /// at once.
+    ///
+    /// If other threads are concurrently modifying the stack, `pred` may get called more than once
+    /// for the same value.
+    ///
+    /// Values are inspected while still on the stack, where a concurrent `pop` might be moving
+    /// them out at the same time. That is why this method requires `T: Copy`: a copy moved out by
+    /// another thread doesn't own any resources that could be destroyed while `pred` is reading the
+    /// original.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::Stack;
+    ///
+    /// let s = Stack::new();
+    /// s.push(1);
+    /// s.push(2);
+    /// s.push(3);
+    /// assert_eq!(s.pop_while(|&x| x > 1), vec![3, 2]);
+    /// assert_eq!(s.pop_while(|&x| x > 1), vec![]);
+    /// assert_eq!(s.pop(), Some(1));
+    /// ```
+    pub fn pop_while<F>(&self, mut pred: F) -> Vec<T>
+    where
+        F: FnMut(&T) -> bool,
+        T: Copy + Sync,
+    {
+        epoch::pin(|scope| {
+            let mut head = self.head.load(Acquire, scope);
+            loop {
+                // Find the first node whose value doesn't satisfy the predicate.
+                let mut boundary = head;
+                while let Some(b) = unsafe { boundary.as_ref() } {
+                    if !pred(&b.value) {
+                        break;
+                    }
+                    boundary = b.next.load(Acquire, scope);
+                }
 
-    #[test]
-    fn smoke() {
-        let s = Stack::new();
-        s.push(1);
-        assert_eq!(s.pop(), Some(1));
-        assert_eq!(s.pop(), None);
-    }
+                if boundary.as_raw() == head.as_raw() {
+                    return Vec::new();
+                }
 
-    #[test]
-    fn push_pop() {
-        let s = Stack::new();
-        s.push(1);
-        s.push(2);
-        s.push(3);
-        assert_eq!(s.pop(), Some(3));
-        s.push(4);
-        assert_eq!(s.pop(), Some(4));
-        assert_eq!(s.pop(), Some(2));
-        assert_eq!(s.pop(), Some(1));
-        assert_eq!(s.pop(), None);
-        s.push(5);
-        assert_eq!(s.pop(), Some(5));
-        assert_eq!(s.pop(), None);
+                // Try detaching all nodes before the boundary.
+                match self.head.compare_and_swap(head, boundary, AcqRel, scope) {
+                    Ok(()) => unsafe {
+                        let mut popped = Vec::new();
+                        let mut curr = head;
+                        while curr.as_raw() != boundary.as_raw() {
+                            let c = curr.deref();
+                            popped.push(ptr::read(&c.value));
+                            scope.defer_free(curr);
+                            curr = c.next.load(Relaxed, scope);
+                        }
+                        return popped;
+                    },
+                    Err(h) => head = h,
+                }
+            }
+        })
     }
 
-    #[test]
-    fn is_empty() {
-        let s = Stack::new();
-        assert!(s.is_empty());
-
-        for i in 0..3 {
-            s.push(i);
-            assert!(!s.is_empty());
+    /// Pops up to `max` values from the top of the stack at once.
+    ///
+    /// The values are returned in the order they were popped. Unlike calling [`pop`] in a loop,
+    /// all of them are detached from the stack with a single CAS, so they are guaranteed to have
+    /// been adjacent on the stack. If other threads get in the way, only that CAS is retried.
+    ///
+    /// If the stack holds fewer than `max` values, all of them are popped.
+    ///
+    /// [`pop`]: struct.Stack.html#method.pop
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::Stack;
+    ///
+    /// let s = Stack::new();
+    /// for i in 0..5 {
+    ///     s.push(i);
+    /// }
+    ///
+    /// assert_eq!(s.try_pop_batch(3), vec![4, 3, 2]);
+    /// assert_eq!(s.try_pop_batch(3), vec![1, 0]);
+    /// assert_eq!(s.try_pop_batch(3), vec![]);
+    /// ```
+    pub fn try_pop_batch(&self, max: usize) -> Vec<T> {
+        if max == 0 {
+            return Vec::new();
         }
 
-        for _ in 0..3 {
-            assert!(!s.is_empty());
-            s.pop();
-        }
+        epoch::pin(|scope| self.pop_prefix(max, scope))
+    }
 
-        assert!(s.is_empty());
-        s.push(3);
+    /// Moves up to `n` values from the top of this stack onto the top of `dest`.
+    ///
+    /// The values are detached from this stack with a single CAS and pushed onto `dest` with
+    /// another one, keeping their order: the value that was on top of this stack ends up on top of
+    /// `dest`. Returns the number of moved values.
+    ///
+    /// Between the two steps the moved values are in neither stack, so a concurrent observer might
+    /// briefly see them missing. No value is ever lost or duplicated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::Stack;
+    ///
+    /// let src = Stack::new();
+    /// let dest = Stack::new();
+    /// for i in 0..5 {
+    ///     src.push(i);
+    /// }
+    ///
+    /// assert_eq!(src.transfer_to(&dest, 3), 3);
+    /// assert_eq!(dest.pop(), Some(4));
+    /// assert_eq!(dest.pop(), Some(3));
+    /// assert_eq!(dest.pop(), Some(2));
+    /// assert_eq!(src.pop(), Some(1));
+    ///
+    /// assert_eq!(src.transfer_to(&dest, 100), 1);
+    /// assert_eq!(dest.pop(), Some(0));
+    /// ```
+    pub fn transfer_to(&self, dest: &Stack<T>, n: usize) -> usize {
+        if n == 0 {
+            return 0;
+        }
+
+        epoch::pin(|scope| {
+            let values = self.pop_prefix(n, scope);
+            let count = values.len();
+            dest.push_chain(values, scope);
+            count
+        })
+    }
+
+    /// Removes consecutive repeated values from the stack, keeping only the first of each run.
+    ///
+    /// This is similar to `Vec::dedup`. The whole stack is detached, the values are deduplicated,
+    /// and then they are pushed back in fresh nodes, in the same order.
+    ///
+    /// This operation is best-effort under concurrency: while the stack is detached it appears
+    /// empty to other threads, and values pushed by them in the meantime will end up below the
+    /// deduplicated ones.
+    ///
+    /// If comparing values panics, the values are still pushed back onto the stack, although
+    /// some of the duplicates may already be removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::Stack;
+    ///
+    /// let s = Stack::new();
+    /// for &x in &[3, 2, 2, 1, 1, 1] {
+    ///     s.push(x);
+    /// }
+    /// s.dedup_adjacent();
+    ///
+    /// assert_eq!(s.pop(), Some(1));
+    /// assert_eq!(s.pop(), Some(2));
+    /// assert_eq!(s.pop(), Some(3));
+    /// assert_eq!(s.pop(), None);
+    /// ```
+    pub fn dedup_adjacent(&self)
+    where
+        T: PartialEq,
+    {
+        epoch::pin(|scope| {
+            // Detach all nodes and move their values out.
+            let mut chain = Detached {
+                head: self.head.swap(Ptr::null(), AcqRel, scope),
+                scope,
+            };
+            let mut values = Vec::new();
+            while let Some(value) = chain.pop() {
+                values.push(value);
+            }
+
+            // The values are pushed back even if comparing them panics, so none of them is lost.
+            // The old nodes cannot be relinked because threads that are concurrently popping
+            // might still be holding onto them.
+            let mut values = guard(values, |values| {
+                self.push_chain(mem::take(values), scope);
+            });
+            values.dedup();
+        })
+    }
+
+    /// Keeps only the values for which `pred` returns `true` and returns the removed ones.
+    ///
+    /// The whole stack is detached, the values are split, and the retained values are pushed back
+    /// in fresh nodes, in the same order. The removed values are returned ordered from top to
+    /// bottom.
+    ///
+    /// Just like [`dedup_adjacent`], this is best-effort under concurrency: while the stack is
+    /// detached it appears empty to other threads, and values pushed by them in the meantime will
+    /// end up below the retained ones.
+    ///
+    /// If `pred` panics, all values are pushed back onto the stack and none is removed.
+    ///
+    /// [`dedup_adjacent`]: struct.Stack.html#method.dedup_adjacent
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::Stack;
+    ///
+    /// let s = Stack::new();
+    /// for i in 0..6 {
+    ///     s.push(i);
+    /// }
+    ///
+    /// assert_eq!(s.retain_into(|&x| x % 2 == 0), vec![5, 3, 1]);
+    /// assert_eq!(s.pop(), Some(4));
+    /// assert_eq!(s.pop(), Some(2));
+    /// assert_eq!(s.pop(), Some(0));
+    /// assert_eq!(s.pop(), None);
+    /// ```
+    pub fn retain_into<F>(&self, mut pred: F) -> Vec<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        epoch::pin(|scope| {
+            // Detach all nodes and move their values out.
+            let mut chain = Detached {
+                head: self.head.swap(Ptr::null(), AcqRel, scope),
+                scope,
+            };
+            let mut values = Vec::new();
+            while let Some(value) = chain.pop() {
+                values.push(value);
+            }
+
+            // Whatever is left in `values` gets pushed back, so if `pred` panics, all values are
+            // restored to the stack.
+            let mut values = guard(values, |values| {
+                self.push_chain(mem::take(values), scope);
+            });
+            let keep = values.iter().map(&mut pred).collect::<Vec<_>>();
+
+            let mut retained = Vec::new();
+            let mut removed = Vec::new();
+            for (value, keep) in mem::take(&mut *values).into_iter().zip(keep) {
+                if keep {
+                    retained.push(value);
+                } else {
+                    removed.push(value);
+                }
+            }
+            *values = retained;
+            removed
+        })
+    }
+
+    /// Pops all values from the stack and passes them to `f`, ordered from top to bottom.
+    ///
+    /// The whole stack is detached at once, so values pushed by other threads in the meantime are
+    /// left on the stack. Unlike popping the values into a vector, this doesn't allocate.
+    ///
+    /// If `f` panics, the values that haven't been passed to it yet are dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::Stack;
+    ///
+    /// let s = Stack::new();
+    /// s.push(1);
+    /// s.push(2);
+    /// s.push(3);
+    ///
+    /// let mut sum = 0;
+    /// s.pop_all_into(|x| sum += x);
+    /// assert_eq!(sum, 6);
+    /// assert!(s.is_empty());
+    /// ```
+    pub fn pop_all_into<F>(&self, mut f: F)
+    where
+        F: FnMut(T),
+    {
+        epoch::pin(|scope| {
+            let mut chain = Detached {
+                head: self.head.swap(Ptr::null(), AcqRel, scope),
+                scope,
+            };
+            while let Some(value) = chain.pop() {
+                f(value);
+            }
+        })
+    }
+
+    /// Walks the stack from top to bottom, threading the state `init` through `f`, and collects
+    /// the values it returns until it returns `None`.
+    ///
+    /// This works like `Iterator::scan` over a snapshot of the stack. The snapshot is best-effort:
+    /// values pushed or popped by other threads during the walk may or may not be observed. Since
+    /// the values are read in place while other threads may be popping them, `T` must be `Copy`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::Stack;
+    ///
+    /// let s = Stack::new();
+    /// s.push(3);
+    /// s.push(2);
+    /// s.push(1);
+    ///
+    /// // Running totals from the top.
+    /// let totals = s.scan(0, |sum, &x| {
+    ///     *sum += x;
+    ///     Some(*sum)
+    /// });
+    /// assert_eq!(totals, vec![1, 3, 6]);
+    /// ```
+    pub fn scan<St, B, F>(&self, init: St, mut f: F) -> Vec<B>
+    where
+        F: FnMut(&mut St, &T) -> Option<B>,
+        T: Copy + Sync,
+    {
+        epoch::pin(|scope| {
+            let mut state = init;
+            let mut result = Vec::new();
+
+            let mut curr = self.head.load(Acquire, scope);
+            while let Some(c) = unsafe { curr.as_ref() } {
+                match f(&mut state, &c.value) {
+                    None => break,
+                    Some(b) => result.push(b),
+                }
+                curr = c.next.load(Acquire, scope);
+            }
+            result
+        })
+    }
+
+    /// Walks the stack from top to bottom and returns the first `Some` produced by `f`.
+    ///
+    /// The walk stops as soon as `f` returns `Some`. Just like with [`scan`], it operates on a
+    /// best-effort snapshot of the stack and `T` must be `Copy`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::Stack;
+    ///
+    /// let s = Stack::new();
+    /// s.push(1);
+    /// s.push(4);
+    /// s.push(9);
+    ///
+    /// assert_eq!(s.find_map(|&x| if x < 5 { Some(x * 10) } else { None }), Some(40));
+    /// assert_eq!(s.find_map(|&x| if x > 10 { Some(x) } else { None }), None);
+    /// ```
+    ///
+    /// [`scan`]: struct.Stack.html#method.scan
+    pub fn find_map<B, F>(&self, mut f: F) -> Option<B>
+    where
+        F: FnMut(&T) -> Option<B>,
+        T: Copy + Sync,
+    {
+        epoch::pin(|scope| {
+            let mut curr = self.head.load(Acquire, scope);
+            while let Some(c) = unsafe { curr.as_ref() } {
+                if let Some(b) = f(&c.value) {
+                    return Some(b);
+                }
+                curr = c.next.load(Acquire, scope);
+            }
+            None
+        })
+    }
+
+    /// Returns an iterator over chunks of up to `size` values, from top to bottom.
+    ///
+    /// The values are copied into a snapshot when this method is called, so the stack is not
+    /// consumed and later changes to it are not reflected in the chunks. If other threads are
+    /// pushing or popping at the same time, the snapshot is best-effort, just like with [`scan`].
+    /// Only the last chunk may be shorter than `size`.
+    ///
+    /// [`scan`]: struct.Stack.html#method.scan
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::Stack;
+    ///
+    /// let s = Stack::new();
+    /// for i in 0..5 {
+    ///     s.push(i);
+    /// }
+    ///
+    /// let chunks = s.chunks(2).collect::<Vec<_>>();
+    /// assert_eq!(chunks, vec![vec![4, 3], vec![2, 1], vec![0]]);
+    /// ```
+    pub fn chunks(&self, size: usize) -> Chunks<T>
+    where
+        T: Copy + Sync,
+    {
+        assert!(size > 0, "chunk size must be non-zero");
+
+        let values = epoch::pin(|scope| {
+            let mut values = Vec::new();
+            let mut curr = self.head.load(Acquire, scope);
+            while let Some(c) = unsafe { curr.as_ref() } {
+                values.push(c.value);
+                curr = c.next.load(Acquire, scope);
+            }
+            values
+        });
+
+        Chunks {
+            values: values.into_iter(),
+            size,
+        }
+    }
+
+    /// Applies `f` to every value in the stack in place, from top to bottom.
+    ///
+    /// Mutating values that other threads might be reading at the same time would be a data race,
+    /// so this method borrows the stack mutably. That guarantees no other thread is accessing it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::Stack;
+    ///
+    /// let mut s = Stack::new();
+    /// s.push(1);
+    /// s.push(2);
+    /// s.update_each(|x| *x *= 10);
+    ///
+    /// assert_eq!(s.pop(), Some(20));
+    /// assert_eq!(s.pop(), Some(10));
+    /// ```
+    pub fn update_each<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut T),
+    {
+        unsafe {
+            epoch::unprotected(|scope| {
+                let mut curr = self.head.load(Relaxed, scope).as_raw() as *mut Node<T>;
+                while !curr.is_null() {
+                    f(&mut (*curr).value);
+                    curr = (*curr).next.load(Relaxed, scope).as_raw() as *mut Node<T>;
+                }
+            })
+        }
+    }
+
+    /// Moves all values into a vector, lets `f` modify it, and rebuilds the stack from it.
+    ///
+    /// The vector is ordered from top to bottom, so its first element ends up on the top of the
+    /// stack. `f` may add, remove, and reorder values arbitrarily. This method borrows the stack
+    /// mutably, which guarantees that no other thread is accessing it in the meantime.
+    ///
+    /// If `f` panics, the stack is still rebuilt from whatever the vector holds at that point.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::Stack;
+    ///
+    /// let mut s = Stack::new();
+    /// for &x in &[2, 3, 1] {
+    ///     s.push(x);
+    /// }
+    /// s.with_contents_mut(|v| v.sort());
+    ///
+    /// assert_eq!(s.pop(), Some(1));
+    /// assert_eq!(s.pop(), Some(2));
+    /// assert_eq!(s.pop(), Some(3));
+    /// ```
+    pub fn with_contents_mut<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut Vec<T>),
+    {
+        let values = mem::replace(self, Stack::new()).into_vec();
+        let mut values = guard(values, |values| *self = Stack::from_vec(mem::take(values)));
+        f(&mut values);
+    }
+
+    /// Consumes the stack and returns all its values in a vector, ordered from top to bottom.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::Stack;
+    ///
+    /// let s = Stack::new();
+    /// s.push(1);
+    /// s.push(2);
+    /// s.push(3);
+    /// assert_eq!(s.into_vec(), vec![3, 2, 1]);
+    /// ```
+    pub fn into_vec(self) -> Vec<T> {
+        let mut values = Vec::new();
+
+        // Move all values out of the stack and free the nodes. The stack is owned, so no other
+        // thread can be accessing it. The head is set to null so that `drop` finds no nodes.
+        unsafe {
+            epoch::unprotected(|scope| {
+                let mut curr = self.head.swap(Ptr::null(), Relaxed, scope).as_raw();
+                while !curr.is_null() {
+                    let Node { value, next } = *Box::from_raw(curr as *mut Node<T>);
+                    values.push(value);
+                    curr = next.load(Relaxed, scope).as_raw();
+                }
+            })
+        }
+
+        values
+    }
+
+    /// Consumes the stack and splits it into two stacks.
+    ///
+    /// The first returned stack contains all values for which `pred` returns `true`, and the second
+    /// one contains all values for which it returns `false`. The relative order of the values is
+    /// preserved in both stacks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::Stack;
+    ///
+    /// let s = Stack::new();
+    /// for i in 0..5 {
+    ///     s.push(i);
+    /// }
+    ///
+    /// let (even, odd) = s.partition(|&x| x % 2 == 0);
+    /// assert_eq!(even.pop(), Some(4));
+    /// assert_eq!(even.pop(), Some(2));
+    /// assert_eq!(even.pop(), Some(0));
+    /// assert_eq!(odd.pop(), Some(3));
+    /// assert_eq!(odd.pop(), Some(1));
+    /// ```
+    pub fn partition<F>(self, pred: F) -> (Stack<T>, Stack<T>)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let (left, right): (Vec<T>, Vec<T>) = self.into_vec().into_iter().partition(pred);
+
+        (Stack::from_vec(left), Stack::from_vec(right))
+    }
+
+    /// Consumes the stack and builds a new one out of the values for which `f` returns `Some`.
+    ///
+    /// The relative order of the values is preserved.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::Stack;
+    ///
+    /// let s = Stack::new();
+    /// for i in 0..5 {
+    ///     s.push(i);
+    /// }
+    ///
+    /// let s = s.filter_map_into(|x| if x % 2 == 0 { Some(x * 10) } else { None });
+    /// assert_eq!(s.into_vec(), vec![40, 20, 0]);
+    /// ```
+    pub fn filter_map_into<U, F>(self, f: F) -> Stack<U>
+    where
+        F: FnMut(T) -> Option<U>,
+    {
+        Stack::from_vec(self.into_vec().into_iter().filter_map(f).collect())
+    }
+
+    /// Consumes the stack and folds its values from top to bottom, stopping at the first error.
+    ///
+    /// If `f` returns `Err(e)`, folding stops and `e` is returned along with a new stack holding
+    /// the values that haven't been passed to `f` yet, in their original order. The value that
+    /// caused the error has already been consumed by `f`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use coco::Stack;
+    ///
+    /// let s = Stack::new();
+    /// for i in 0..5 {
+    ///     s.push(i);
+    /// }
+    ///
+    /// let (e, rest) = s.try_fold(0, |acc, x| if x == 2 { Err(acc) } else { Ok(acc + x) })
+    ///     .unwrap_err();
+    /// assert_eq!(e, 7);
+    /// assert_eq!(rest.into_vec(), vec![1, 0]);
+    /// ```
+    pub fn try_fold<B, E, F>(self, init: B, mut f: F) -> Result<B, (E, Stack<T>)>
+    where
+        F: FnMut(B, T) -> Result<B, E>,
+    {
+        let mut iter = self.into_vec().into_iter();
+        let mut acc = init;
+
+        for value in iter.by_ref() {
+            match f(acc, value) {
+                Ok(b) => acc = b,
+                Err(e) => return Err((e, Stack::from_vec(iter.collect()))),
+            }
+        }
+        Ok(acc)
+    }
+
+    /// Detaches up to `n` nodes from the top of the stack with a single CAS and moves their values
+    /// out, ordered from top to bottom.
+    fn pop_prefix(&self, n: usize, scope: &Scope) -> Vec<T> {
+        let mut head = self.head.load(Acquire, scope);
+        let (count, boundary) = loop {
+            // Find the node right after the first `n` nodes.
+            let mut count = 0;
+            let mut boundary = head;
+            while count < n {
+                match unsafe { boundary.as_ref() } {
+                    None => break,
+                    Some(b) => boundary = b.next.load(Acquire, scope),
+                }
+                count += 1;
+            }
+
+            if count == 0 {
+                return Vec::new();
+            }
+
+            // Try detaching all nodes before the boundary.
+            match self.head.compare_and_swap(head, boundary, AcqRel, scope) {
+                Ok(()) => break (count, boundary),
+                Err(h) => head = h,
+            }
+        };
+
+        // Move the values out of the detached nodes.
+        let mut values = Vec::with_capacity(count);
+        let mut curr = head;
+        while curr.as_raw() != boundary.as_raw() {
+            unsafe {
+                let c = curr.deref();
+                values.push(ptr::read(&c.value));
+                scope.defer_free(curr);
+                curr = c.next.load(Relaxed, scope);
+            }
+        }
+        values
+    }
+
+    /// Pushes `values`, ordered from top to bottom, onto the stack in fresh nodes.
+    ///
+    /// The nodes are linked together first, so the whole chain is installed with a single CAS.
+    fn push_chain(&self, values: Vec<T>, scope: &Scope) {
+        // Build the chain starting from the bottom.
+        let mut iter = values.into_iter().rev();
+        let bottom = match iter.next() {
+            None => return,
+            Some(value) => Owned::new(Node {
+                value,
+                next: Atomic::null(),
+            }).into_ptr(scope),
+        };
+        let mut top = bottom;
+        for value in iter {
+            top = Owned::new(Node {
+                value,
+                next: Atomic::from_ptr(top),
+            }).into_ptr(scope);
+        }
+
+        // Put the new chain onto the stack.
+        let bottom = unsafe { bottom.deref() };
+        let mut head = self.head.load(Acquire, scope);
+        loop {
+            bottom.next.store(head, Relaxed);
+            match self.head.compare_and_swap_weak(head, top, AcqRel, scope) {
+                Ok(()) => break,
+                Err(h) => head = h,
+            }
+        }
+    }
+
+    /// Returns a new stack containing `values`, ordered from top to bottom.
+    fn from_vec(values: Vec<T>) -> Self {
+        let s = Stack::new();
+        for value in values.into_iter().rev() {
+            s.push(value);
+        }
+        s
+    }
+}
+
+/// Cloning reads values while they are still on the stack, where a concurrent `pop` might be
+/// moving them out at the same time. That is why cloning requires `T: Copy`, just like
+/// [`pop_while`].
+///
+/// [`pop_while`]: struct.Stack.html#method.pop_while
+impl<T: Copy + Sync> Clone for Stack<T> {
+    fn clone(&self) -> Self {
+        let mut s = Stack::new();
+        s.clone_from(self);
+        s
+    }
+
+    /// Makes this stack a copy of `source`, reusing the already allocated nodes.
+    ///
+    /// If `source` is being concurrently modified, the copy is a best-effort snapshot.
+    fn clone_from(&mut self, source: &Self) {
+        // Take a snapshot of the values in the source stack.
+        let values = epoch::pin(|scope| {
+            let mut values = Vec::new();
+            let mut curr = source.head.load(Acquire, scope);
+            while let Some(c) = unsafe { curr.as_ref() } {
+                values.push(c.value);
+                curr = c.next.load(Acquire, scope);
+            }
+            values
+        });
+
+        // This stack is borrowed mutably, so no other thread can be accessing it. Overwrite the
+        // values in the existing nodes and allocate new nodes only if there are not enough.
+        unsafe {
+            epoch::unprotected(|scope| {
+                let mut link: *const Atomic<Node<T>> = &self.head;
+                for value in values {
+                    let mut curr = (*link).load(Relaxed, scope).as_raw() as *mut Node<T>;
+                    if curr.is_null() {
+                        let new = Owned::new(Node {
+                            value,
+                            next: Atomic::null(),
+                        }).into_ptr(scope);
+                        (*link).store(new, Relaxed);
+                        curr = new.as_raw() as *mut Node<T>;
+                    } else {
+                        (*curr).value = value;
+                    }
+                    link = &(*curr).next;
+                }
+
+                // Free the remaining nodes.
+                let mut curr = (*link).swap(Ptr::null(), Relaxed, scope).as_raw();
+                while !curr.is_null() {
+                    let next = (*curr).next.load(Relaxed, scope).as_raw();
+                    drop(Box::from_raw(curr as *mut Node<T>));
+                    curr = next;
+                }
+            })
+        }
+    }
+}
+
+/// Hashes a snapshot of the values in the stack, ordered from top to bottom.
+///
+/// The hash is the same as the hash of a vector holding the values in that order. It is only
+/// meaningful if no other thread is modifying the stack at the same time, so that the snapshot
+/// is exact. Hashing a stack that is being concurrently modified gives an arbitrary result, which
+/// can differ between two hashes of the same stack.
+///
+/// Hashing reads values while they are still on the stack, which is why it requires `T: Copy`,
+/// just like [`pop_while`].
+///
+/// [`pop_while`]: struct.Stack.html#method.pop_while
+///
+/// # Examples
+///
+/// ```
+/// use coco::Stack;
+/// use std::collections::hash_map::DefaultHasher;
+/// use std::hash::{Hash, Hasher};
+///
+/// fn hash<T: Hash>(t: &T) -> u64 {
+///     let mut h = DefaultHasher::new();
+///     t.hash(&mut h);
+///     h.finish()
+/// }
+///
+/// let s = Stack::new();
+/// s.push(1);
+/// s.push(2);
+/// assert_eq!(hash(&s), hash(&vec![2, 1]));
+/// ```
+impl<T: Hash + Copy + Sync> Hash for Stack<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let values = epoch::pin(|scope| {
+            let mut values = Vec::new();
+            let mut curr = self.head.load(Acquire, scope);
+            while let Some(c) = unsafe { curr.as_ref() } {
+                values.push(c.value);
+                curr = c.next.load(Acquire, scope);
+            }
+            values
+        });
+        values.hash(state);
+    }
+}
+
+impl<T> Stack<T> {
+    /// Destructs all nodes in the stack, exactly how dropping the stack would.
+    unsafe fn destroy_nodes(&mut self) {
+        epoch::unprotected(|scope| {
+            let mut curr = self.head.load(Relaxed, scope);
+            while !curr.is_null() {
+                let node = Box::from_raw(curr.as_raw() as *mut Node<T>);
+                curr = node.next.load(Relaxed, scope);
+
+                // If the value's destructor panics, the rest of the nodes are owned by `rest`,
+                // which destructs them while unwinding. This is how `Vec` behaves, too.
+                let rest = Stack {
+                    head: Atomic::from_ptr(curr),
+                    _marker: PhantomData,
+                };
+                drop(node);
+                mem::forget(rest);
+            }
+        })
+    }
+}
+
+#[cfg(not(feature = "nightly"))]
+impl<T> Drop for Stack<T> {
+    fn drop(&mut self) {
+        unsafe { self.destroy_nodes() }
+    }
+}
+
+/// Dropping a stack only drops its values and never otherwise accesses them, so the drop checker
+/// allows the values to contain dangling references at that point, just like with `Vec`.
+#[cfg(feature = "nightly")]
+unsafe impl<#[may_dangle] T> Drop for Stack<T> {
+    fn drop(&mut self) {
+        unsafe { self.destroy_nodes() }
+    }
+}
+
+/// An iterator over chunks of a snapshot of a stack.
+///
+/// This `struct` is created by the [`chunks`] method on [`Stack`].
+///
+/// [`chunks`]: struct.Stack.html#method.chunks
+/// [`Stack`]: struct.Stack.html
+#[derive(Debug)]
+pub struct Chunks<T> {
+    values: vec::IntoIter<T>,
+    size: usize,
+}
+
+impl<T> Iterator for Chunks<T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Vec<T>> {
+        let chunk = self.values.by_ref().take(self.size).collect::<Vec<_>>();
+        if chunk.is_empty() { None } else { Some(chunk) }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.values.len();
+        let n = if len == 0 { 0 } else { (len - 1) / self.size + 1 };
+        (n, Some(n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT};
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::thread;
+
+    use super::Stack;
+    use self::rand::Rng;
+
+    #[test]
+    fn drop_panic() {
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Elem(i32);
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, SeqCst);
+                if self.0 == 5 {
+                    panic!("dropping element 5");
+                }
+            }
+        }
+
+        let s = Stack::new();
+        for i in 0..10 {
+            s.push(Elem(i));
+        }
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| drop(s)));
+        assert!(result.is_err());
+        assert_eq!(DROPS.load(SeqCst), 10);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn may_dangle() {
+        // `x` is dropped before `s`, so `s` holds a dangling reference while being dropped.
+        let s;
+        let x = 7;
+        s = Stack::new();
+        s.push(&x);
+        assert_eq!(s.pop(), Some(&7));
+        s.push(&x);
+    }
+
+    #[test]
+    fn smoke() {
+        let s = Stack::new();
+        s.push(1);
+        assert_eq!(s.pop(), Some(1));
+        assert_eq!(s.pop(), None);
+    }
+
+    #[test]
+    fn push_pop() {
+        let s = Stack::new();
+        s.push(1);
+        s.push(2);
+        s.push(3);
+        assert_eq!(s.pop(), Some(3));
+        s.push(4);
+        assert_eq!(s.pop(), Some(4));
+        assert_eq!(s.pop(), Some(2));
+        assert_eq!(s.pop(), Some(1));
+        assert_eq!(s.pop(), None);
+        s.push(5);
+        assert_eq!(s.pop(), Some(5));
+        assert_eq!(s.pop(), None);
+    }
+
+    #[test]
+    fn pop_while() {
+        let s = Stack::new();
+        assert_eq!(s.pop_while(|_| true), vec![]);
+
+        for i in 0..10 {
+            s.push(i);
+        }
+        assert_eq!(s.pop_while(|_| false), vec![]);
+        assert_eq!(s.pop_while(|&x| x >= 7), vec![9, 8, 7]);
+
+        for i in (0..7).rev() {
+            assert_eq!(s.pop(), Some(i));
+        }
+        assert_eq!(s.pop(), None);
+    }
+
+    #[test]
+    fn push_unique() {
+        let s = Stack::new();
+        assert!(s.push_unique(1));
+        assert!(s.push_unique(2));
+        assert!(!s.push_unique(1));
+        assert!(!s.push_unique(2));
+        assert!(s.push_unique(3));
+
+        assert_eq!(s.pop(), Some(3));
+        assert_eq!(s.pop(), Some(2));
+        assert_eq!(s.pop(), Some(1));
+        assert_eq!(s.pop(), None);
+
+        assert!(s.push_unique(1));
+        assert_eq!(s.pop(), Some(1));
+    }
+
+    #[test]
+    fn push_unique_stress() {
+        const THREADS: usize = 8;
+
+        let s = Arc::new(Stack::new());
+
+        let threads = (0..THREADS).map(|_| {
+            let s = s.clone();
+
+            thread::spawn(move || {
+                let mut rng = rand::thread_rng();
+                for _ in 0..10_000 {
+                    if rng.gen() {
+                        s.push_unique(rng.gen_range(0, 100));
+                    } else {
+                        s.pop();
+                    }
+                }
+            })
+        }).collect::<Vec<_>>();
+
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        while let Some(x) = s.pop() {
+            assert!(x < 100);
+        }
+    }
+
+    #[test]
+    fn retain_into() {
+        let s = Stack::new();
+        assert_eq!(s.retain_into(|_: &String| true), Vec::<String>::new());
+
+        for i in 0..20 {
+            s.push(i.to_string());
+        }
+        let strings = |rem| (0..20).rev().filter(move |i| i % 2 == rem).map(|i| i.to_string());
+        let removed = s.retain_into(|x| x.parse::<i32>().unwrap() % 2 == 0);
+        assert_eq!(removed, strings(1).collect::<Vec<_>>());
+
+        assert_eq!(s.retain_into(|_| true), Vec::<String>::new());
+        assert_eq!(s.into_vec(), strings(0).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn retain_into_panic() {
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Elem(i32);
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, SeqCst);
+            }
+        }
+
+        let s = Stack::new();
+        for i in 0..10 {
+            s.push(Elem(i));
+        }
+
+        // No value is leaked nor dropped, and all of them are back on the stack in order.
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            s.retain_into(|e| {
+                if e.0 == 5 {
+                    panic!("found element 5");
+                }
+                e.0 % 2 == 0
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(DROPS.load(SeqCst), 0);
+
+        let values = s.into_vec();
+        let expected = (0..10).rev().collect::<Vec<_>>();
+        assert_eq!(values.iter().map(|e| e.0).collect::<Vec<_>>(), expected);
+        drop(values);
+        assert_eq!(DROPS.load(SeqCst), 10);
+    }
+
+    #[test]
+    fn transfer_to() {
+        let a = Stack::new();
+        let b = Stack::new();
+        assert_eq!(a.transfer_to(&b, 10), 0);
+
+        for i in 0..10 {
+            a.push(i);
+        }
+        assert_eq!(a.transfer_to(&b, 0), 0);
+        assert_eq!(a.transfer_to(&b, 4), 4);
+        assert_eq!(b.transfer_to(&a, 1), 1);
+        assert_eq!(a.into_vec(), vec![9, 5, 4, 3, 2, 1, 0]);
+        assert_eq!(b.into_vec(), vec![8, 7, 6]);
+    }
+
+    #[test]
+    fn transfer_to_stress() {
+        const THREADS: usize = 4;
+        const COUNT: usize = 10_000;
+
+        let stacks = Arc::new([Stack::new(), Stack::new()]);
+        for i in 0..COUNT {
+            stacks[i % 2].push(i);
+        }
+
+        // Shuffle values back and forth between the two stacks.
+        let threads = (0..THREADS).map(|t| {
+            let stacks = stacks.clone();
+            thread::spawn(move || {
+                for i in 0..1000 {
+                    let (src, dest) = if (t + i) % 2 == 0 { (0, 1) } else { (1, 0) };
+                    stacks[src].transfer_to(&stacks[dest], i % 16);
+                }
+            })
+        }).collect::<Vec<_>>();
+
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        let stacks = Arc::try_unwrap(stacks).ok().unwrap();
+        let mut all = Vec::new();
+        for s in stacks.iter() {
+            while let Some(x) = s.pop() {
+                all.push(x);
+            }
+        }
+        all.sort();
+        assert_eq!(all, (0..COUNT).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn try_pop_batch() {
+        let s = Stack::new();
+        assert_eq!(s.try_pop_batch(3), vec![]);
+
+        for i in 0..5 {
+            s.push(i);
+        }
+        assert_eq!(s.try_pop_batch(0), vec![]);
+        assert_eq!(s.try_pop_batch(2), vec![4, 3]);
+        assert_eq!(s.try_pop_batch(10), vec![2, 1, 0]);
+        assert_eq!(s.try_pop_batch(1), vec![]);
+        assert_eq!(s.pop(), None);
+    }
+
+    #[test]
+    fn try_pop_batch_stress() {
+        const PUSHERS: usize = 2;
+        const POPPERS: usize = 2;
+        const COUNT: usize = 10_000;
+
+        let s = Arc::new(Stack::new());
+        let remaining = Arc::new(AtomicUsize::new(PUSHERS * COUNT));
+
+        let pushers = (0..PUSHERS).map(|t| {
+            let s = s.clone();
+            thread::spawn(move || {
+                for i in 0..COUNT {
+                    s.push(t * COUNT + i);
+                }
+            })
+        }).collect::<Vec<_>>();
+
+        let poppers = (0..POPPERS).map(|t| {
+            let s = s.clone();
+            let remaining = remaining.clone();
+            thread::spawn(move || {
+                let mut popped = Vec::new();
+                let mut i = t;
+                while remaining.load(SeqCst) > 0 {
+                    let batch = s.try_pop_batch(1 + i % 16);
+                    assert!(batch.len() <= 1 + i % 16);
+                    remaining.fetch_sub(batch.len(), SeqCst);
+                    if batch.is_empty() {
+                        thread::yield_now();
+                    }
+                    popped.extend(batch);
+                    i += 1;
+                }
+                popped
+            })
+        }).collect::<Vec<_>>();
+
+        for t in pushers {
+            t.join().unwrap();
+        }
+
+        let mut all = Vec::new();
+        for t in poppers {
+            all.extend(t.join().unwrap());
+        }
+        assert_eq!(s.pop(), None);
+
+        all.sort();
+        assert_eq!(all, (0..PUSHERS * COUNT).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn dedup_adjacent() {
+        let s = Stack::new();
+        s.dedup_adjacent();
+        assert_eq!(s.pop(), None);
+
+        for &x in &["c", "b", "b", "b", "a", "a"] {
+            s.push(x);
+        }
+        s.dedup_adjacent();
+        assert_eq!(s.pop(), Some("a"));
+        assert_eq!(s.pop(), Some("b"));
+        assert_eq!(s.pop(), Some("c"));
+        assert_eq!(s.pop(), None);
+
+        for &x in &["a", "b", "a", "a"] {
+            s.push(x);
+        }
+        s.dedup_adjacent();
+        assert_eq!(s.pop(), Some("a"));
+        assert_eq!(s.pop(), Some("b"));
+        assert_eq!(s.pop(), Some("a"));
+        assert_eq!(s.pop(), None);
+    }
+
+    #[test]
+    fn dedup_adjacent_panic() {
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Elem(i32);
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, SeqCst);
+            }
+        }
+
+        impl PartialEq for Elem {
+            fn eq(&self, other: &Elem) -> bool {
+                if self.0 == 5 || other.0 == 5 {
+                    panic!("compared element 5");
+                }
+                self.0 == other.0
+            }
+        }
+
+        let s = Stack::new();
+        for i in 0..10 {
+            s.push(Elem(i));
+        }
+
+        // No value is leaked nor dropped, and all of them are back on the stack.
+        let result = panic::catch_unwind(AssertUnwindSafe(|| s.dedup_adjacent()));
+        assert!(result.is_err());
+        assert_eq!(DROPS.load(SeqCst), 0);
+
+        let values = s.into_vec();
+        let expected = (0..10).rev().collect::<Vec<_>>();
+        assert_eq!(values.iter().map(|e| e.0).collect::<Vec<_>>(), expected);
+        drop(values);
+        assert_eq!(DROPS.load(SeqCst), 10);
+    }
+
+    #[test]
+    fn into_vec() {
+        assert_eq!(Stack::<i32>::new().into_vec(), vec![]);
+
+        let s = Stack::new();
+        for i in 0..10 {
+            s.push(i);
+        }
+        s.pop();
+        assert_eq!(s.into_vec(), (0..9).rev().collect::<Vec<_>>());
+
+        struct Elem(Arc<AtomicUsize>);
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, SeqCst);
+            }
+        }
+
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let s = Stack::new();
+        for _ in 0..10 {
+            s.push(Elem(dropped.clone()));
+        }
+        let v = s.into_vec();
+        assert_eq!(dropped.load(SeqCst), 0);
+        drop(v);
+        assert_eq!(dropped.load(SeqCst), 10);
+    }
+
+    #[test]
+    fn partition() {
+        let s = Stack::new();
+        for i in 0..10 {
+            s.push(i);
+        }
+
+        let (even, odd) = s.partition(|&x| x % 2 == 0);
+        for i in (0..10).rev().filter(|x| x % 2 == 0) {
+            assert_eq!(even.pop(), Some(i));
+        }
+        for i in (0..10).rev().filter(|x| x % 2 == 1) {
+            assert_eq!(odd.pop(), Some(i));
+        }
+        assert!(even.is_empty());
+        assert!(odd.is_empty());
+
+        let (all, none) = Stack::<i32>::new().partition(|_| true);
+        assert!(all.is_empty());
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn filter_map_into() {
+        let s = Stack::new();
+        for i in 0..10 {
+            s.push(i);
+        }
+
+        let s = s.filter_map_into(|x| if x % 2 == 0 { Some(x.to_string()) } else { None });
+        for &x in &["8", "6", "4", "2", "0"] {
+            assert_eq!(s.pop(), Some(x.to_string()));
+        }
+        assert_eq!(s.pop(), None);
+    }
+
+    #[test]
+    fn try_fold() {
+        let s = Stack::new();
+        for i in 0..10 {
+            s.push(i);
+        }
+
+        let (e, rest) = s
+            .try_fold(Vec::new(), |mut seen, x| {
+                if x == 6 {
+                    Err(seen)
+                } else {
+                    seen.push(x);
+                    Ok(seen)
+                }
+            })
+            .unwrap_err();
+        assert_eq!(e, vec![9, 8, 7]);
+        assert_eq!(rest.into_vec(), vec![5, 4, 3, 2, 1, 0]);
+
+        let s = Stack::new();
+        for i in 0..10 {
+            s.push(i);
+        }
+        let sum = s.try_fold(0, |acc, x| Ok::<_, ()>(acc + x));
+        assert_eq!(sum.ok(), Some(45));
+    }
+
+    #[test]
+    fn clone() {
+        let s = Stack::new();
+        for i in 0..10 {
+            s.push(i);
+        }
+
+        let c = s.clone();
+        assert_eq!(c.into_vec(), (0..10).rev().collect::<Vec<_>>());
+        assert_eq!(s.pop(), Some(9));
+
+        let mut c = Stack::new();
+        for i in 0..20 {
+            c.push(i + 100);
+        }
+        c.clone_from(&s);
+        assert_eq!(c.clone().into_vec(), (0..9).rev().collect::<Vec<_>>());
+
+        let mut c = Stack::new();
+        c.push(100);
+        c.clone_from(&s);
+        assert_eq!(c.clone().into_vec(), (0..9).rev().collect::<Vec<_>>());
+
+        c.clone_from(&Stack::new());
+        assert!(c.is_empty());
+    }
+
+    #[test]
+    fn push_if_empty() {
+        const THREADS: usize = 8;
+
+        for _ in 0..100 {
+            let s = Arc::new(Stack::new());
+
+            let threads = (0..THREADS).map(|t| {
+                let s = s.clone();
+                thread::spawn(move || s.push_if_empty(t).is_ok())
+            }).collect::<Vec<_>>();
+
+            let seeded = threads.into_iter().map(|t| t.join().unwrap());
+            assert_eq!(seeded.filter(|&ok| ok).count(), 1);
+
+            assert!(s.pop().is_some());
+            assert!(s.pop().is_none());
+        }
+    }
+
+    #[test]
+    fn cas_push() {
+        const THREADS: usize = 8;
+        const COUNT: usize = 10_000;
+
+        let s = Arc::new(Stack::new());
+
+        let threads = (0..THREADS).map(|t| {
+            let s = s.clone();
+
+            thread::spawn(move || {
+                for i in 0..COUNT {
+                    // Retry with a bounded, growing backoff.
+                    let mut value = t * COUNT + i;
+                    let mut backoff = 1;
+                    while let Err(v) = s.cas_push(value) {
+                        value = v;
+                        for _ in 0..backoff {
+                            thread::yield_now();
+                        }
+                        backoff = (backoff * 2).min(16);
+                    }
+                }
+            })
+        }).collect::<Vec<_>>();
+
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        let mut all = s.pop_while(|_| true);
+        all.sort();
+        assert_eq!(all, (0..THREADS * COUNT).collect::<Vec<_>>());
+
+        // Without contention, a single attempt always succeeds.
+        let s = Stack::new();
+        for i in 0..COUNT {
+            assert_eq!(s.cas_push(i), Ok(()));
+        }
+    }
+
+    #[test]
+    fn compare_top_and_pop() {
+        const THREADS: usize = 8;
+        const COUNT: usize = 10_000;
+
+        let s = Arc::new(Stack::new());
+        for i in 0..COUNT {
+            s.push(i);
+        }
+
+        // Every thread tries popping the value it believes to be on the top.
+        let threads = (0..THREADS).map(|_| {
+            let s = s.clone();
+
+            thread::spawn(move || {
+                let mut popped = Vec::new();
+                let mut expected = COUNT - 1;
+                loop {
+                    match s.compare_top_and_pop(&expected) {
+                        Ok(x) => {
+                            assert_eq!(x, expected);
+                            popped.push(x);
+                            expected = x.wrapping_sub(1);
+                        }
+                        Err(Some(actual)) => {
+                            assert!(actual < expected);
+                            expected = actual;
+                        }
+                        Err(None) => return popped,
+                    }
+                }
+            })
+        }).collect::<Vec<_>>();
+
+        let mut all = Vec::new();
+        for t in threads {
+            all.extend(t.join().unwrap());
+        }
+        all.sort();
+        assert_eq!(all, (0..COUNT).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn pop_all_into() {
+        let s = Stack::new();
+        s.pop_all_into(|_: i32| panic!());
+
+        for i in 0..100 {
+            s.push(i);
+        }
+        let mut popped = Vec::new();
+        s.pop_all_into(|x| popped.push(x));
+        assert_eq!(popped, (0..100).rev().collect::<Vec<_>>());
+        assert!(s.is_empty());
+
+        for i in 1..11 {
+            s.push(i);
+        }
+        let mut sum = 0;
+        s.pop_all_into(|x| sum += x);
+        assert_eq!(sum, 55);
+        assert!(s.is_empty());
+    }
+
+    #[test]
+    fn pop_all_into_panic() {
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Elem(i32);
+
+        impl Drop for Elem {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, SeqCst);
+            }
+        }
+
+        let s = Stack::new();
+        for i in 0..10 {
+            s.push(Elem(i));
+        }
+
+        // The values after the one that caused the panic are dropped as well.
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            s.pop_all_into(|e| {
+                if e.0 == 5 {
+                    panic!("found element 5");
+                }
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(DROPS.load(SeqCst), 10);
+        assert!(s.is_empty());
+    }
+
+    #[test]
+    fn scan() {
+        let s = Stack::new();
+        assert_eq!(s.scan(0, |_, &x: &i32| Some(x)), vec![]);
+
+        for i in 1..11 {
+            s.push(i);
+        }
+
+        let totals = s.scan(0, |sum, &x| {
+            *sum += x;
+            Some(*sum)
+        });
+        assert_eq!(totals, vec![10, 19, 27, 34, 40, 45, 49, 52, 54, 55]);
+
+        // Stop once the running total exceeds 30.
+        let prefix = s.scan(0, |sum, &x| {
+            *sum += x;
+            if *sum > 30 { None } else { Some(x) }
+        });
+        assert_eq!(prefix, vec![10, 9, 8]);
+
+        // The stack is left untouched.
+        assert_eq!(s.pop(), Some(10));
+    }
+
+    #[test]
+    fn chunks() {
+        let s = Stack::new();
+        assert_eq!(s.chunks(3).next(), None::<Vec<i32>>);
+
+        for i in 0..10 {
+            s.push(i);
+        }
+
+        let chunks = s.chunks(3);
+        assert_eq!(chunks.size_hint(), (4, Some(4)));
+        let chunks = chunks.collect::<Vec<_>>();
+        assert_eq!(chunks.iter().map(|c| c.len()).collect::<Vec<_>>(), vec![3, 3, 3, 1]);
+        assert_eq!(chunks.concat(), (0..10).rev().collect::<Vec<_>>());
+
+        // The snapshot is unaffected by later changes, and the stack is left untouched.
+        let mut chunks = s.chunks(10);
+        s.push(10);
+        assert_eq!(chunks.next(), Some((0..10).rev().collect()));
+        assert_eq!(chunks.next(), None);
+        assert_eq!(s.pop(), Some(10));
+        assert_eq!(s.pop(), Some(9));
+    }
+
+    #[test]
+    fn find_map() {
+        let s = Stack::new();
+        assert_eq!(s.find_map(|&x: &i32| Some(x)), None);
+
+        for i in 0..10 {
+            s.push(i);
+        }
+
+        // The walk stops at the first match, counting from the top.
+        let mut visited = 0;
+        let found = s.find_map(|&x| {
+            visited += 1;
+            if x % 4 == 0 { Some(x * 100) } else { None }
+        });
+        assert_eq!(found, Some(800));
+        assert_eq!(visited, 2);
+
+        assert_eq!(s.find_map(|&x| if x > 9 { Some(x) } else { None }), None);
+        assert_eq!(s.pop(), Some(9));
+    }
+
+    #[test]
+    fn update_each() {
+        let mut s = Stack::new();
+        s.update_each(|_: &mut String| panic!());
+
+        for i in 0..10 {
+            s.push(i.to_string());
+        }
+
+        let mut visited = Vec::new();
+        s.update_each(|x| {
+            visited.push(x.clone());
+            x.push('!');
+        });
+        assert_eq!(visited, (0..10).rev().map(|i| i.to_string()).collect::<Vec<_>>());
+
+        for i in (0..10).rev() {
+            assert_eq!(s.pop(), Some(format!("{}!", i)));
+        }
+        assert_eq!(s.pop(), None);
+    }
+
+    #[test]
+    fn with_contents_mut() {
+        let mut s = Stack::new();
+        s.with_contents_mut(|v| v.push(1));
+        assert_eq!(s.pop(), Some(1));
+        assert_eq!(s.pop(), None);
+
+        for &x in &[4, 1, 3, 0, 2] {
+            s.push(x);
+        }
+        s.with_contents_mut(|v| {
+            assert_eq!(*v, vec![2, 0, 3, 1, 4]);
+            v.sort();
+            v.push(5);
+        });
+        for i in 0..6 {
+            assert_eq!(s.pop(), Some(i));
+        }
+        assert_eq!(s.pop(), None);
+
+        // The stack is rebuilt even if the closure panics.
+        for i in 0..5 {
+            s.push(i);
+        }
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            s.with_contents_mut(|v| {
+                v.reverse();
+                panic!();
+            })
+        }));
+        assert!(res.is_err());
+        assert_eq!(s.into_vec(), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn hash() {
+        fn hash<T: Hash>(t: &T) -> u64 {
+            let mut h = DefaultHasher::new();
+            t.hash(&mut h);
+            h.finish()
+        }
+
+        let build = |values: &[i32]| {
+            let s = Stack::new();
+            for &x in values {
+                s.push(x);
+            }
+            s
+        };
+
+        assert_eq!(hash(&Stack::<i32>::new()), hash(&Stack::<i32>::new()));
+        assert_eq!(hash(&build(&[1, 2, 3])), hash(&build(&[1, 2, 3])));
+        assert_eq!(hash(&build(&[1, 2, 3])), hash(&vec![3, 2, 1]));
+        assert_ne!(hash(&build(&[1, 2, 3])), hash(&build(&[3, 2, 1])));
+        assert_ne!(hash(&build(&[1, 2, 3])), hash(&build(&[1, 2])));
+        assert_ne!(hash(&build(&[0])), hash(&Stack::<i32>::new()));
+    }
+
+    #[test]
+    fn len_at_least() {
+        let s = Stack::new();
+        assert!(s.len_at_least(0));
+        assert!(!s.len_at_least(1));
+
+        for i in 0..10 {
+            s.push(i);
+        }
+        for n in 0..11 {
+            assert!(s.len_at_least(n));
+        }
+        assert!(!s.len_at_least(11));
+        assert!(!s.len_at_least(usize::MAX));
+
+        s.pop();
+        assert!(s.len_at_least(9));
+        assert!(!s.len_at_least(10));
+    }
+
+    #[test]
+    fn is_empty() {
+        let s = Stack::new();
+        assert!(s.is_empty());
+
+        for i in 0..3 {
+            s.push(i);
+            assert!(!s.is_empty());
+        }
+
+        for _ in 0..3 {
+            assert!(!s.is_empty());
+            s.pop();
+        }
+
+        assert!(s.is_empty());
+        s.push(3);
         assert!(!s.is_empty());
         s.pop();
         assert!(s.is_empty());
     }
 
+    #[test]
+    fn boxed_closures() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let s: Stack<Box<Fn() -> usize + Send>> = Stack::new();
+
+        for i in 0..10 {
+            let calls = calls.clone();
+            s.push(Box::new(move || {
+                calls.fetch_add(1, SeqCst);
+                i
+            }));
+        }
+
+        for i in (0..10).rev() {
+            let f = s.pop().unwrap();
+            assert_eq!(f(), i);
+        }
+        assert!(s.pop().is_none());
+        assert_eq!(calls.load(SeqCst), 10);
+        assert_eq!(Arc::strong_count(&calls), 1);
+    }
+
     #[test]
     fn stress() {
         const THREADS: usize = 8;
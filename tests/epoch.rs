@@ -0,0 +1,49 @@
+//! Tests of the epoch GC that need a process of their own.
+//!
+//! When the last registered thread exits, it reclaims all outstanding garbage. Unit tests can't
+//! check that because threads from other tests are registered at the same time, so this file
+//! must contain only one test, and the test thread itself must never be pinned.
+
+extern crate coco;
+
+use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT};
+use std::sync::atomic::Ordering::SeqCst;
+use std::thread;
+
+use coco::epoch::{self, Owned};
+
+#[test]
+fn last_exit_reclaims_everything() {
+    const THREADS: usize = 8;
+    // With the `strict_gc` feature this is tens of thousands of bags.
+    const COUNT: usize = 10_000;
+    static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+    struct Elem;
+
+    impl Drop for Elem {
+        fn drop(&mut self) {
+            DROPS.fetch_add(1, SeqCst);
+        }
+    }
+
+    fn produce() {
+        epoch::pin(|scope| unsafe {
+            for _ in 0..COUNT {
+                scope.defer_drop(Owned::new(Elem).into_ptr(scope));
+            }
+        });
+    }
+
+    let threads = (0..THREADS).map(|_| thread::spawn(produce)).collect::<Vec<_>>();
+    for t in threads {
+        t.join().unwrap();
+    }
+
+    // The last participant exits with the default stack size, which must be enough to destroy all
+    // the expired bags at once.
+    thread::Builder::new().stack_size(2 << 20).spawn(produce).unwrap().join().unwrap();
+
+    // No participant is left, so everything has been reclaimed without any help.
+    assert_eq!(DROPS.load(SeqCst), (THREADS + 1) * COUNT);
+}